@@ -168,6 +168,23 @@ where
     pub fn on_connect_ext<F>(mut self, f: F) -> Self
     where
         F: Fn(&T, &mut Extensions) + 'static,
+    {
+        self.on_connect_ext = Some(Rc::new(
+            move |io: &T, data: &mut Extensions, _: &mut Extensions| f(io, data),
+        ));
+        self
+    }
+
+    /// Sets the callback to be run on connection establishment, with access to connection data.
+    ///
+    /// The first data container is merged into request extensions, as with
+    /// [`on_connect_ext`](Self::on_connect_ext). Values inserted into the second are shared by all
+    /// requests on the connection and available through
+    /// [`RequestHead::conn_data`](crate::RequestHead::conn_data). Replaces a callback set with
+    /// `on_connect_ext`.
+    pub fn on_connect_conn_data<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T, &mut Extensions, &mut Extensions) + 'static,
     {
         self.on_connect_ext = Some(Rc::new(f));
         self
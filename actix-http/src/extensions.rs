@@ -124,6 +124,11 @@ impl Extensions {
         self.map.extend(other.map);
     }
 
+    /// Returns `true` if the container holds no items.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
     /// Sets (or overrides) items from `other` into this map.
     pub(crate) fn drain_from(&mut self, other: &mut Self) {
         self.map.extend(mem::take(&mut other.map));
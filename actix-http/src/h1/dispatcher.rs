@@ -1048,7 +1048,7 @@ mod tests {
         h1::{ExpectHandler, UpgradeHandler},
         http::Method,
         test::{TestBuffer, TestSeqBuffer},
-        HttpMessage, KeepAlive,
+        Extensions, HttpMessage, KeepAlive,
     };
 
     fn find_slice(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
@@ -1132,6 +1132,63 @@ mod tests {
         .await;
     }
 
+    #[actix_rt::test]
+    async fn test_conn_data_shared_by_pipelined_requests() {
+        lazy(|cx| {
+            let buf = TestBuffer::new(
+                "\
+                GET /abcd HTTP/1.1\r\n\r\n\
+                GET /def HTTP/1.1\r\n\r\n\
+                ",
+            );
+
+            let cfg = ServiceConfig::new(KeepAlive::Disabled, 1, 1, false, None);
+
+            let services = HttpFlow::new(
+                fn_service(|req: Request| {
+                    let body = match req.conn_data().and_then(|data| data.get::<&str>())
+                    {
+                        Some(data) => AnyBody::from_slice(data.as_bytes()),
+                        None => AnyBody::from_slice(b"none"),
+                    };
+                    ready(Ok::<_, Error>(Response::ok().set_body(body)))
+                }),
+                ExpectHandler,
+                None,
+            );
+
+            let mut data = Extensions::new();
+            data.insert("conn");
+
+            let h1 = Dispatcher::<_, _, _, _, UpgradeHandler>::new(
+                buf,
+                cfg,
+                services,
+                OnConnectData {
+                    ext: None,
+                    conn_data: Some(Rc::new(data)),
+                },
+                None,
+            );
+
+            actix_rt::pin!(h1);
+
+            match h1.as_mut().poll(cx) {
+                Poll::Pending => panic!("first poll should not be pending"),
+                Poll::Ready(res) => assert!(res.is_ok()),
+            }
+
+            if let DispatcherStateProj::Normal(inner) = h1.project().inner.project() {
+                let res = &inner.project().io.take().unwrap().write_buf[..];
+
+                let first = find_slice(res, b"\r\n\r\nconn", 0).unwrap();
+                assert!(find_slice(res, b"\r\n\r\nconn", first + 1).is_some());
+                assert!(find_slice(res, b"none", 0).is_none());
+            }
+        })
+        .await;
+    }
+
     #[actix_rt::test]
     async fn test_pipelining() {
         lazy(|cx| {
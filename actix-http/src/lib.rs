@@ -27,6 +27,8 @@
 #[macro_use]
 extern crate log;
 
+use std::rc::Rc;
+
 pub mod body;
 mod builder;
 pub mod client;
@@ -98,17 +100,26 @@ pub enum Protocol {
     Http3,
 }
 
-type ConnectCallback<IO> = dyn Fn(&IO, &mut Extensions);
+/// Fills the data merged into request extensions and the connection data, in that order.
+type ConnectCallback<IO> = dyn Fn(&IO, &mut Extensions, &mut Extensions);
 
 /// Container for data that extract with ConnectCallback.
 ///
 /// # Implementation Details
-/// Uses Option to reduce necessary allocations when merging with request extensions.
-pub(crate) struct OnConnectData(Option<Extensions>);
+/// Uses Option to reduce necessary allocations when merging with request extensions. Connection
+/// data is only allocated if the callback set any; it is reference counted so every request on
+/// the connection shares it.
+pub(crate) struct OnConnectData {
+    pub(crate) ext: Option<Extensions>,
+    pub(crate) conn_data: Option<Rc<Extensions>>,
+}
 
 impl Default for OnConnectData {
     fn default() -> Self {
-        Self(None)
+        Self {
+            ext: None,
+            conn_data: None,
+        }
     }
 }
 
@@ -118,20 +129,32 @@ impl OnConnectData {
         io: &T,
         on_connect_ext: Option<&ConnectCallback<T>>,
     ) -> Self {
+        let mut conn_data = None;
+
         let ext = on_connect_ext.map(|handler| {
             let mut extensions = Extensions::new();
-            handler(io, &mut extensions);
+            let mut shared = Extensions::new();
+            handler(io, &mut extensions, &mut shared);
+
+            if !shared.is_empty() {
+                conn_data = Some(Rc::new(shared));
+            }
+
             extensions
         });
 
-        Self(ext)
+        Self { ext, conn_data }
     }
 
-    /// Merge self into given request's extensions.
+    /// Merge self into given request's extensions and attach the connection data.
     #[inline]
     pub(crate) fn merge_into(&mut self, req: &mut Request) {
-        if let Some(ref mut ext) = self.0 {
+        if let Some(ref mut ext) = self.ext {
             req.head.extensions.get_mut().drain_from(ext);
         }
+
+        if self.conn_data.is_some() {
+            req.head.set_conn_data(self.conn_data.clone());
+        }
     }
 }
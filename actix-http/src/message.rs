@@ -52,6 +52,7 @@ pub struct RequestHead {
     pub headers: HeaderMap,
     pub extensions: RefCell<Extensions>,
     pub peer_addr: Option<net::SocketAddr>,
+    conn_data: Option<Rc<Extensions>>,
     flags: Flags,
 }
 
@@ -64,6 +65,7 @@ impl Default for RequestHead {
             headers: HeaderMap::with_capacity(16),
             flags: Flags::empty(),
             peer_addr: None,
+            conn_data: None,
             extensions: RefCell::new(Extensions::new()),
         }
     }
@@ -74,6 +76,7 @@ impl Head for RequestHead {
         self.flags = Flags::empty();
         self.headers.clear();
         self.extensions.get_mut().clear();
+        self.conn_data = None;
     }
 
     fn with_pool<F, R>(f: F) -> R
@@ -97,6 +100,22 @@ impl RequestHead {
         self.extensions.borrow_mut()
     }
 
+    /// Connection data set by an
+    /// [`on_connect_conn_data`](crate::HttpServiceBuilder::on_connect_conn_data) callback.
+    ///
+    /// Unlike the message extensions, this container is shared by all requests on the same
+    /// connection and cannot be modified after the connection is accepted.
+    #[inline]
+    pub fn conn_data(&self) -> Option<&Extensions> {
+        self.conn_data.as_deref()
+    }
+
+    /// Attaches the shared connection data of the connection this request arrived on.
+    #[inline]
+    pub(crate) fn set_conn_data(&mut self, data: Option<Rc<Extensions>>) {
+        self.conn_data = data;
+    }
+
     /// Read the message headers.
     pub fn headers(&self) -> &HeaderMap {
         &self.headers
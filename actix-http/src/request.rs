@@ -119,6 +119,12 @@ impl<P> Request<P> {
         &mut self.head_mut().headers
     }
 
+    /// Connection data shared by all requests on the connection, see [`RequestHead::conn_data`].
+    #[inline]
+    pub fn conn_data(&self) -> Option<&Extensions> {
+        self.head().conn_data()
+    }
+
     /// Request's uri.
     #[inline]
     pub fn uri(&self) -> &Uri {
@@ -16,7 +16,7 @@ use http::{Method, Uri, Version};
 use crate::{
     header::{HeaderMap, IntoHeaderPair},
     payload::Payload,
-    Request,
+    Extensions, Request,
 };
 
 /// Test `Request` builder
@@ -50,6 +50,7 @@ struct Inner {
     uri: Uri,
     headers: HeaderMap,
     payload: Option<Payload>,
+    conn_data: Option<Extensions>,
 }
 
 impl Default for TestRequest {
@@ -60,6 +61,7 @@ impl Default for TestRequest {
             version: Version::HTTP_11,
             headers: HeaderMap::new(),
             payload: None,
+            conn_data: None,
         }))
     }
 }
@@ -133,6 +135,14 @@ impl TestRequest {
         self
     }
 
+    /// Set the connection data, see [`RequestHead::conn_data`].
+    ///
+    /// [`RequestHead::conn_data`]: crate::RequestHead::conn_data
+    pub fn conn_data(&mut self, data: Extensions) -> &mut Self {
+        parts(&mut self.0).conn_data = Some(data);
+        self
+    }
+
     pub fn take(&mut self) -> TestRequest {
         TestRequest(self.0.take())
     }
@@ -152,6 +162,7 @@ impl TestRequest {
         head.method = inner.method;
         head.version = inner.version;
         head.headers = inner.headers;
+        head.set_conn_data(inner.conn_data.map(Rc::new));
 
         req
     }
@@ -0,0 +1,149 @@
+use std::{cell::RefCell, rc::Rc};
+
+use actix_http::{test::TestRequest as HttpTestRequest, Extensions, Request};
+use actix_service::Service as _;
+use actix_web::{
+    dev::{ConnectionInfo, TlsInfo, Transport},
+    test::{init_service, TestRequest},
+    web, App, HttpRequest, HttpResponse,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn bench_connection_info(c: &mut Criterion) {
+    let mut group = c.benchmark_group("connection_info");
+
+    group.bench_function("resolve", |b| {
+        b.iter_with_setup(
+            || {
+                TestRequest::default()
+                    .insert_header((
+                        "forwarded",
+                        "for=192.0.2.60; proto=https; host=rust-lang.org",
+                    ))
+                    .to_http_request()
+            },
+            |req| {
+                black_box(req.connection_info().host().len());
+                req
+            },
+        )
+    });
+
+    // common case of a single proxy without a Forwarded header
+    group.bench_function("resolve_x_forwarded_for", |b| {
+        b.iter_with_setup(
+            || {
+                TestRequest::default()
+                    .peer_addr("10.0.0.1:4711".parse().unwrap())
+                    .insert_header(("x-forwarded-for", "192.0.2.60"))
+                    .to_http_request()
+            },
+            |req| {
+                black_box(req.connection_info().host().len());
+                req
+            },
+        )
+    });
+
+    // only connection-constant inputs, as on a keep-alive connection without a proxy
+    group.bench_function("resolve_connection_constants", |b| {
+        b.iter_with_setup(
+            || {
+                TestRequest::default()
+                    .peer_addr("192.0.2.60:4711".parse().unwrap())
+                    .sni("rust-lang.org")
+                    .alpn("h2")
+                    .to_http_request()
+            },
+            |req| {
+                black_box(req.connection_info().scheme().len());
+                req
+            },
+        )
+    });
+
+    group.bench_function("cached", |b| {
+        let req = TestRequest::default()
+            .insert_header((
+                "forwarded",
+                "for=192.0.2.60; proto=https; host=rust-lang.org",
+            ))
+            .to_http_request();
+
+        // populate cache
+        let _ = req.connection_info();
+
+        b.iter(|| {
+            black_box(
+                ConnectionInfo::get(req.head(), req.app_config())
+                    .host()
+                    .len(),
+            )
+        })
+    });
+
+    group.finish();
+}
+
+// requests on a keep-alive connection, whose connection data has the connection-constant inputs
+// recorded once as by `HttpServer`, against the same data looked up again for every request
+fn bench_connection_constants(c: &mut Criterion) {
+    let rt = actix_rt::System::new();
+
+    // the app only captures the request so resolution can be repeated on the same connection data
+    let captured = Rc::new(RefCell::new(None));
+    let app = rt.block_on(init_service(App::new().default_service(web::to({
+        let captured = Rc::clone(&captured);
+        move |req: HttpRequest| {
+            *captured.borrow_mut() = Some(req);
+            HttpResponse::Ok()
+        }
+    }))));
+    let capture = |req: Request| {
+        rt.block_on(app.call(req)).unwrap();
+        captured.borrow_mut().take().unwrap()
+    };
+
+    let tls = || TlsInfo {
+        sni: Some("rust-lang.org".to_owned()),
+        alpn: Some("h2".to_owned()),
+    };
+
+    let recorded = capture(
+        TestRequest::default()
+            .insert_header(("host", "rust-lang.org"))
+            .conn_data(tls())
+            .conn_data(Transport::Tcp)
+            .to_request(),
+    );
+
+    let mut data = Extensions::new();
+    data.insert(tls());
+    data.insert(Transport::Tcp);
+    let not_recorded = capture(
+        HttpTestRequest::default()
+            .insert_header(("host", "rust-lang.org"))
+            .conn_data(data)
+            .finish(),
+    );
+
+    let mut group = c.benchmark_group("connection_constants");
+
+    for (name, req) in &[("recorded", recorded), ("not_recorded", not_recorded)] {
+        group.bench_function(*name, |b| {
+            b.iter(|| {
+                // as if the next request on the connection had arrived
+                req.extensions_mut().remove::<ConnectionInfo>();
+
+                let info = req.connection_info();
+                black_box(info.host_matches_sni(req.head()));
+                black_box(info.transport());
+            })
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_connection_info, bench_connection_constants);
+criterion_main!(benches);
@@ -13,6 +13,7 @@ use crate::{
     config::{AppConfig, AppService},
     data::FnDataFactory,
     guard::Guard,
+    info::AppHeadConfig,
     request::{HttpRequest, HttpRequestPool},
     rmap::ResourceMap,
     service::{AppServiceFactory, ServiceRequest, ServiceResponse},
@@ -135,10 +136,13 @@ where
                 factory.create(&mut app_data);
             }
 
+            let head_cfg = AppHeadConfig::new(&config, &app_data);
+
             Ok(AppInitService {
                 service,
                 app_data: Rc::new(app_data),
                 app_state: AppInitServiceState::new(rmap, config),
+                head_cfg,
             })
         })
     }
@@ -154,6 +158,7 @@ where
     service: T,
     app_data: Rc<Extensions>,
     app_state: Rc<AppInitServiceState>,
+    head_cfg: Option<AppHeadConfig>,
 }
 
 /// A collection of [`AppInitService`] state that shared across `HttpRequest`s.
@@ -202,6 +207,11 @@ where
     fn call(&self, req: Request) -> Self::Future {
         let (head, payload) = req.into_parts();
 
+        // lets guards, which only see the request head, resolve the scheme with the app's config
+        if let Some(ref head_cfg) = self.head_cfg {
+            head.extensions_mut().insert(head_cfg.clone());
+        }
+
         let req = if let Some(mut req) = self.app_state.pool().pop() {
             let inner = Rc::get_mut(&mut req.inner).unwrap();
             inner.path.get_mut().update(&head.uri);
@@ -12,9 +12,26 @@
 pub use crate::config::{AppConfig, AppService};
 #[doc(hidden)]
 pub use crate::handler::Handler;
-pub use crate::info::{ConnectionInfo, PeerAddr};
+#[cfg(feature = "otel-attributes")]
+pub use crate::info::OtelValue;
+#[cfg(feature = "reverse-dns")]
+pub use crate::info::PeerHostnameResolver;
+#[cfg(feature = "registrable-domain")]
+pub use crate::info::PublicSuffixList;
+pub use crate::info::{
+    parse_forwarded, AbsoluteUri, CertifiedHost, CertifiedHostConfig, ClientIpError, Conn,
+    ConnectionData, ConnectionEstablishedAt, ConnectionInfo, ConnectionInfoBorrowed,
+    ConnectionInfoConfig, ConnectionInfoError, ConnectionInfoFields, ConnectionInfoResolver,
+    DefaultResolver, ForwardingHeadersPresent, HostPreference, Http2Negotiation, LogClientIp,
+    MisdirectedRequest, ParseTrustedProxiesError, PeerAddr, QuicPeerAddr, RealIpSource,
+    RealIpStrategy, RequiredClientIp, ResolvedClientIp, SchemeSource, StickyKey,
+    StrictConnectionInfo, TlsInfo, TooManyHops, Transport, TrustedProxies, TryConnectionInfo,
+    TunnelInfo, ViaHop,
+};
 pub use crate::rmap::ResourceMap;
 pub use crate::service::{HttpServiceFactory, ServiceRequest, ServiceResponse, WebService};
+/// IP network type used by [`TrustedProxies`] and [`PeerAddr::is_in`], re-exported from `ipnet`.
+pub use ipnet::IpNet;
 
 pub use crate::types::form::UrlEncoded;
 pub use crate::types::json::JsonBody;
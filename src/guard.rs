@@ -32,6 +32,8 @@ use std::rc::Rc;
 use actix_http::http::{self, header, uri::Uri};
 use actix_http::RequestHead;
 
+use crate::info::head_is_secure;
+
 /// Trait defines resource guards. Guards are used for route selection.
 ///
 /// Guards can not modify the request object. But it is possible
@@ -329,12 +331,72 @@ impl Guard for HostGuard {
     }
 }
 
+/// Return guard that matches if the connection is [secure](crate::dev::ConnectionInfo::is_secure).
+///
+/// Guards only see the request head, so the request's connection info is used if it was already
+/// resolved, eg. by a middleware calling
+/// [`ServiceRequest::connection_info`](crate::dev::ServiceRequest::connection_info). Otherwise the
+/// scheme is resolved with the [`ConnectionInfoConfig`](crate::dev::ConnectionInfoConfig) set in
+/// app-level data, so forwarding headers are only honored as that config allows. Without one,
+/// forwarding headers are ignored and only TLS and the request URI are considered.
+///
+/// ```
+/// use actix_web::{guard, web, App, HttpResponse};
+///
+/// App::new().service(
+///     web::resource("/login")
+///         .guard(guard::Secure())
+///         .to(|| HttpResponse::Ok())
+/// );
+/// ```
+pub fn Secure() -> SecureGuard {
+    SecureGuard(true)
+}
+
+/// Return guard that matches if the connection is not
+/// [secure](crate::dev::ConnectionInfo::is_secure).
+///
+/// Useful for routing plain HTTP requests to a redirect handler; see [`Secure`] for how the
+/// scheme is resolved.
+///
+/// ```
+/// use actix_web::{guard, http::header, web, App, HttpRequest, HttpResponse};
+///
+/// App::new().default_service(
+///     web::route()
+///         .guard(guard::Insecure())
+///         .to(|req: HttpRequest| {
+///             let location = format!("https://{}{}", req.connection_info().host(), req.uri());
+///             HttpResponse::PermanentRedirect()
+///                 .insert_header((header::LOCATION, location))
+///                 .finish()
+///         }),
+/// );
+/// ```
+pub fn Insecure() -> SecureGuard {
+    SecureGuard(false)
+}
+
+#[doc(hidden)]
+pub struct SecureGuard(bool);
+
+impl Guard for SecureGuard {
+    fn check(&self, req: &RequestHead) -> bool {
+        head_is_secure(req) == self.0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use actix_http::http::{header, Method};
 
     use super::*;
-    use crate::test::TestRequest;
+    use crate::config::AppConfig;
+    use crate::dev::{ConnectionInfo, ConnectionInfoConfig, Extensions, TrustedProxies};
+    use crate::http::StatusCode;
+    use crate::info::AppHeadConfig;
+    use crate::test::{call_service, init_service, TestRequest};
+    use crate::{web, App, HttpResponse};
 
     #[test]
     fn test_header() {
@@ -504,4 +566,114 @@ mod tests {
         assert!(Any(Get()).or(Trace()).check(r.head()));
         assert!(!Any(Get()).or(Get()).check(r.head()));
     }
+
+    #[actix_rt::test]
+    async fn test_secure() {
+        let srv = init_service(
+            App::new().service(
+                web::resource("/")
+                    .route(
+                        web::get()
+                            .guard(Insecure())
+                            .to(HttpResponse::PermanentRedirect),
+                    )
+                    .route(web::get().guard(Secure()).to(HttpResponse::Ok)),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::default().to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+
+        let req = TestRequest::default().tls(true).to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // forwarding headers are ignored without a config in app data
+        let req = TestRequest::default()
+            .insert_header(("x-forwarded-proto", "https"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+
+        let info_cfg = ConnectionInfoConfig::default()
+            .trusted_proxies(TrustedProxies::new().network("10.0.0.0/8".parse().unwrap()));
+        let srv = init_service(
+            App::new().app_data(info_cfg).service(
+                web::resource("/")
+                    .route(
+                        web::get()
+                            .guard(Insecure())
+                            .to(HttpResponse::PermanentRedirect),
+                    )
+                    .route(web::get().guard(Secure()).to(HttpResponse::Ok)),
+            ),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:50000".parse().unwrap())
+            .insert_header(("x-forwarded-proto", "https"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // spoofed by an untrusted client
+        let req = TestRequest::default()
+            .peer_addr("203.0.113.5:50000".parse().unwrap())
+            .insert_header(("x-forwarded-proto", "https"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+
+        let srv = init_service(
+            App::new()
+                .app_data(ConnectionInfoConfig::default().forwarded_header(false))
+                .service(
+                    web::resource("/")
+                        .guard(Not(Secure()))
+                        .to(HttpResponse::PermanentRedirect),
+                ),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "proto=https"))
+            .to_request();
+        let resp = call_service(&srv, req).await;
+        assert_eq!(resp.status(), StatusCode::PERMANENT_REDIRECT);
+    }
+
+    #[test]
+    fn test_secure_app() {
+        let req = TestRequest::default().to_http_request();
+        assert!(Insecure().check(req.head()));
+
+        let cfg = AppConfig::new(
+            true,
+            "localhost:8443".to_owned(),
+            "127.0.0.1:8443".parse().unwrap(),
+        );
+        let req = TestRequest::default().to_http_request();
+        let head_cfg = AppHeadConfig::new(&cfg, &Extensions::new()).unwrap();
+        req.head().extensions_mut().insert(head_cfg);
+        assert!(Secure().check(req.head()));
+        assert!(!Insecure().check(req.head()));
+
+        // the scheme is cached apart, so config set on resources still applies to connection info
+        assert!(req.extensions().get::<ConnectionInfo>().is_none());
+    }
+
+    #[test]
+    fn test_secure_resolved_info() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "proto=https"))
+            .to_http_request();
+        assert!(Insecure().check(req.head()));
+
+        // connection info resolved with the request's config takes precedence
+        assert_eq!(req.connection_info().scheme(), "https");
+        assert!(Secure().check(req.head()));
+    }
 }
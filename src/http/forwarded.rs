@@ -0,0 +1,477 @@
+use std::{borrow::Cow, convert::TryFrom, fmt, net::Ipv6Addr};
+
+use crate::error::ParseError;
+use crate::http::header::{HeaderValue, IntoHeaderValue, InvalidHeaderValue};
+use crate::info::forwarded::{parse_element, split_elements, unquote};
+
+/// Parsed `Forwarded` header, defined in [RFC 7239].
+///
+/// Holds one [`ForwardedElement`] per proxy hop, in order. Can be used independently of request
+/// handling, eg. to inspect a header value or to build one for an outbound request.
+///
+/// [RFC 7239]: https://datatracker.ietf.org/doc/html/rfc7239
+///
+/// # Examples
+/// ```
+/// use actix_web::http::{header::HeaderValue, Forwarded, ForwardedElement};
+///
+/// let hdr = HeaderValue::from_static("for=192.0.2.60;proto=http;by=203.0.113.43");
+/// let fwd = Forwarded::parse(&hdr).unwrap();
+/// assert_eq!(fwd.elements()[0].for_node(), Some("192.0.2.60"));
+/// assert_eq!(fwd.elements()[0].proto(), Some("http"));
+///
+/// // append this hop before forwarding the request
+/// let mut fwd = fwd;
+/// let mut elem = ForwardedElement::default();
+/// elem.set_for_node("[2001:db8:cafe::17]:4711");
+/// fwd.push(elem);
+/// assert_eq!(
+///     fwd.to_string(),
+///     r#"for=192.0.2.60;by=203.0.113.43;proto=http, for="[2001:db8:cafe::17]:4711""#,
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Forwarded {
+    elements: Vec<ForwardedElement>,
+}
+
+impl Forwarded {
+    /// Constructs an empty `Forwarded` value.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a `Forwarded` header value.
+    ///
+    /// Quoted values are unquoted and their quoted-pairs, eg. `\"`, decoded. Parameters
+    /// other than `for`, `by`, `host` and `proto` and malformed or empty pairs are skipped, as
+    /// are repeated parameters within an element. Fails if the value is not visible ASCII or
+    /// contains no elements.
+    pub fn parse(value: &HeaderValue) -> Result<Self, ParseError> {
+        let elements = parse_elements(value)
+            .map(|(_, elem)| elem)
+            .collect::<Vec<_>>();
+
+        if elements.is_empty() {
+            return Err(ParseError::Header);
+        }
+
+        Ok(Forwarded { elements })
+    }
+
+    /// Elements of the header, one per proxy hop, in order.
+    pub fn elements(&self) -> &[ForwardedElement] {
+        &self.elements
+    }
+
+    /// Unwrap into the header's elements.
+    pub fn into_elements(self) -> Vec<ForwardedElement> {
+        self.elements
+    }
+
+    /// Appends an element.
+    pub fn push(&mut self, elem: ForwardedElement) {
+        self.elements.push(elem);
+    }
+
+    /// The `for` nodes of all elements, in order.
+    pub fn for_nodes(&self) -> impl Iterator<Item = &str> {
+        self.elements.iter().filter_map(ForwardedElement::for_node)
+    }
+
+    /// Returns true if there are no elements.
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+impl fmt::Display for Forwarded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, elem) in self.elements.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(", ")?;
+            }
+
+            write!(f, "{}", elem)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl IntoHeaderValue for Forwarded {
+    type Error = InvalidHeaderValue;
+
+    fn try_into_value(self) -> Result<HeaderValue, Self::Error> {
+        HeaderValue::try_from(self.to_string())
+    }
+}
+
+/// Elements of a `Forwarded` header value, each as sent and parsed, in order.
+///
+/// This is the parser behind [`Forwarded::parse`]; crate code that also needs the raw elements
+/// uses it so it accepts exactly the same elements.
+pub(crate) fn parse_elements(
+    value: &HeaderValue,
+) -> impl Iterator<Item = (&str, ForwardedElement)> {
+    value
+        .to_str()
+        .ok()
+        .into_iter()
+        .flat_map(split_elements)
+        .filter_map(|raw| Some((raw.trim(), ForwardedElement::parse(raw)?)))
+}
+
+/// Single element of a [`Forwarded`] header, describing one proxy hop.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ForwardedElement {
+    by: Option<String>,
+    for_node: Option<String>,
+    host: Option<String>,
+    proto: Option<String>,
+}
+
+impl ForwardedElement {
+    /// Parses an element, returning `None` if it contains no pairs.
+    fn parse(elem: &str) -> Option<Self> {
+        let mut pairs = parse_element(elem).peekable();
+        pairs.peek()?;
+
+        let mut parsed = ForwardedElement::default();
+
+        for (name, val) in pairs {
+            let field = if name.eq_ignore_ascii_case("for") {
+                &mut parsed.for_node
+            } else if name.eq_ignore_ascii_case("by") {
+                &mut parsed.by
+            } else if name.eq_ignore_ascii_case("host") {
+                &mut parsed.host
+            } else if name.eq_ignore_ascii_case("proto") {
+                &mut parsed.proto
+            } else {
+                continue;
+            };
+
+            field.get_or_insert_with(|| unescape(val).into_owned());
+        }
+
+        Some(parsed)
+    }
+
+    /// The interface where the request came in to the proxy (`by`).
+    pub fn by(&self) -> Option<&str> {
+        self.by.as_deref()
+    }
+
+    /// The client or upstream proxy that made the request to the proxy (`for`).
+    pub fn for_node(&self) -> Option<&str> {
+        self.for_node.as_deref()
+    }
+
+    /// The `Host` request header as received by the proxy (`host`).
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    /// The protocol used to make the request to the proxy (`proto`).
+    pub fn proto(&self) -> Option<&str> {
+        self.proto.as_deref()
+    }
+
+    /// Set the `by` node. A bare IPv6 address is enclosed in brackets.
+    pub fn set_by(&mut self, by: impl Into<String>) {
+        self.by = Some(bracket_ipv6(by.into()));
+    }
+
+    /// Set the `for` node. A bare IPv6 address is enclosed in brackets.
+    pub fn set_for_node(&mut self, for_node: impl Into<String>) {
+        self.for_node = Some(bracket_ipv6(for_node.into()));
+    }
+
+    /// Set the `host` value. A bare IPv6 address is enclosed in brackets.
+    pub fn set_host(&mut self, host: impl Into<String>) {
+        self.host = Some(bracket_ipv6(host.into()));
+    }
+
+    /// Set the `proto` value.
+    pub fn set_proto(&mut self, proto: impl Into<String>) {
+        self.proto = Some(proto.into());
+    }
+}
+
+impl fmt::Display for ForwardedElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs = [
+            ("for", &self.for_node),
+            ("by", &self.by),
+            ("host", &self.host),
+            ("proto", &self.proto),
+        ];
+
+        let mut first = true;
+
+        for (name, val) in pairs.iter() {
+            let val = match val {
+                Some(val) => val,
+                None => continue,
+            };
+
+            if !first {
+                f.write_str(";")?;
+            }
+            first = false;
+
+            // values that are not tokens, eg. IPv6 nodes and ports, must be quoted
+            if !val.is_empty() && val.bytes().all(is_token_char) {
+                write!(f, "{}={}", name, val)?;
+            } else {
+                write!(f, "{}=\"", name)?;
+
+                // escape quotes and backslashes so values cannot end the quoted string early
+                for c in val.chars() {
+                    if c == '"' || c == '\\' {
+                        f.write_str("\\")?;
+                    }
+
+                    write!(f, "{}", c)?;
+                }
+
+                f.write_str("\"")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Trim whitespace and decode a quoted string, eg. `"a\"b"` to `a"b`.
+///
+/// Values that are not a well-formed quoted string, eg. with unbalanced quotes, are only
+/// trimmed of whitespace and quote marks, as by [`unquote`].
+fn unescape(val: &str) -> Cow<'_, str> {
+    let inner = match val
+        .trim()
+        .strip_prefix('"')
+        .and_then(|val| val.strip_suffix('"'))
+    {
+        Some(inner) => inner,
+        None => return Cow::Borrowed(unquote(val)),
+    };
+
+    if !inner.contains(|c| c == '"' || c == '\\') {
+        return Cow::Borrowed(inner);
+    }
+
+    let mut unescaped = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some(c) => unescaped.push(c),
+                None => return Cow::Borrowed(unquote(val)),
+            },
+            '"' => return Cow::Borrowed(unquote(val)),
+            c => unescaped.push(c),
+        }
+    }
+
+    Cow::Owned(unescaped)
+}
+
+/// Encloses a bare IPv6 address in brackets, as RFC 7239 §6 requires for nodes.
+fn bracket_ipv6(val: String) -> String {
+    match val.parse::<Ipv6Addr>() {
+        Ok(_) => format!("[{}]", val),
+        Err(_) => val,
+    }
+}
+
+/// Returns true if `b` is a `tchar` as defined in RFC 7230 §3.2.6.
+fn is_token_char(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(val: &'static str) -> Result<Forwarded, ParseError> {
+        Forwarded::parse(&HeaderValue::from_static(val))
+    }
+
+    fn element(
+        for_node: Option<&str>,
+        by: Option<&str>,
+        proto: Option<&str>,
+    ) -> ForwardedElement {
+        ForwardedElement {
+            for_node: for_node.map(ToOwned::to_owned),
+            by: by.map(ToOwned::to_owned),
+            proto: proto.map(ToOwned::to_owned),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rfc7239_examples() {
+        // https://datatracker.ietf.org/doc/html/rfc7239#section-4
+        let fwd = parse(r#"for="_gazonk""#).unwrap();
+        assert_eq!(fwd.elements(), &[element(Some("_gazonk"), None, None)]);
+
+        let fwd = parse(r#"For="[2001:db8:cafe::17]:4711""#).unwrap();
+        assert_eq!(
+            fwd.elements(),
+            &[element(Some("[2001:db8:cafe::17]:4711"), None, None)],
+        );
+
+        let fwd = parse("for=192.0.2.60;proto=http;by=203.0.113.43").unwrap();
+        assert_eq!(
+            fwd.elements(),
+            &[element(
+                Some("192.0.2.60"),
+                Some("203.0.113.43"),
+                Some("http")
+            )],
+        );
+
+        let fwd = parse("for=192.0.2.43, for=198.51.100.17").unwrap();
+        assert_eq!(
+            fwd.for_nodes().collect::<Vec<_>>(),
+            vec!["192.0.2.43", "198.51.100.17"],
+        );
+
+        // https://datatracker.ietf.org/doc/html/rfc7239#section-7.1
+        let fwd = parse(r#"for=192.0.2.43, for="[2001:db8:cafe::17]", for=unknown"#).unwrap();
+        assert_eq!(
+            fwd.for_nodes().collect::<Vec<_>>(),
+            vec!["192.0.2.43", "[2001:db8:cafe::17]", "unknown"],
+        );
+
+        // https://datatracker.ietf.org/doc/html/rfc7239#section-7.4
+        let fwd = parse(
+            "for=192.0.2.43,for=198.51.100.17;by=203.0.113.60;proto=http;host=example.com",
+        )
+        .unwrap();
+        assert_eq!(fwd.elements().len(), 2);
+        assert_eq!(fwd.elements()[1].by(), Some("203.0.113.60"));
+        assert_eq!(fwd.elements()[1].host(), Some("example.com"));
+    }
+
+    #[test]
+    fn lenient_parsing() {
+        // unknown parameters and empty or malformed pairs are skipped
+        let fwd = parse("for=192.0.2.60;;secret=abc;proto=;host, ext=1").unwrap();
+        assert_eq!(
+            fwd.elements(),
+            &[
+                element(Some("192.0.2.60"), None, None),
+                element(None, None, None)
+            ],
+        );
+
+        // first of repeated parameters wins
+        let fwd = parse("for=192.0.2.60;for=198.51.100.17").unwrap();
+        assert_eq!(fwd.for_nodes().collect::<Vec<_>>(), vec!["192.0.2.60"]);
+
+        // empty elements are skipped
+        let fwd = parse(" , for=192.0.2.60,").unwrap();
+        assert_eq!(fwd.elements().len(), 1);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse("").is_err());
+        assert!(parse(" , ;").is_err());
+        assert!(parse("for=").is_err());
+
+        let hdr = HeaderValue::from_bytes(b"for=\xa9").unwrap();
+        assert!(Forwarded::parse(&hdr).is_err());
+    }
+
+    #[test]
+    fn display_round_trip() {
+        for val in &[
+            "for=192.0.2.60;by=203.0.113.43;proto=http",
+            r#"for="[2001:db8:cafe::17]:4711""#,
+            r#"for=192.0.2.43, for="[2001:db8:cafe::17]", for=unknown"#,
+            "for=_gazonk;host=example.com",
+        ] {
+            let fwd = parse(val).unwrap();
+            assert_eq!(&fwd.to_string(), val);
+            assert_eq!(fwd.try_into_value().unwrap(), *val);
+        }
+    }
+
+    #[test]
+    fn quoted_values() {
+        // delimiters inside quoted strings do not split elements or pairs
+        let fwd = parse(r#"for="a,b";host="c;d", for=e"#).unwrap();
+        assert_eq!(fwd.elements().len(), 2);
+        assert_eq!(fwd.elements()[0].for_node(), Some("a,b"));
+        assert_eq!(fwd.elements()[0].host(), Some("c;d"));
+        assert_eq!(fwd.elements()[1].for_node(), Some("e"));
+
+        // quoted-pairs are decoded
+        let fwd = parse(r#"host="a\"b\\c""#).unwrap();
+        assert_eq!(fwd.elements()[0].host(), Some(r#"a"b\c"#));
+    }
+
+    #[test]
+    fn parameter_injection() {
+        let mut elem = ForwardedElement::default();
+        elem.set_host(r#"a";for=evil"#);
+        elem.set_proto(r#"\"#);
+
+        let mut fwd = Forwarded::new();
+        fwd.push(elem.clone());
+        assert_eq!(fwd.to_string(), r#"host="a\";for=evil";proto="\\""#);
+
+        // the value round-trips without adding a `for` parameter
+        let parsed = Forwarded::parse(&fwd.try_into_value().unwrap()).unwrap();
+        assert_eq!(parsed.elements(), &[elem]);
+        assert_eq!(parsed.for_nodes().count(), 0);
+
+        let mut elem = ForwardedElement::default();
+        elem.set_for_node("a, for=evil");
+        let mut fwd = Forwarded::new();
+        fwd.push(elem.clone());
+        let parsed = Forwarded::parse(&fwd.try_into_value().unwrap()).unwrap();
+        assert_eq!(parsed.into_elements(), vec![elem]);
+    }
+
+    #[test]
+    fn build() {
+        let mut elem = ForwardedElement::default();
+        elem.set_for_node("192.0.2.60:4711");
+        elem.set_by("_proxy");
+        elem.set_proto("https");
+        elem.set_host("rust-lang.org");
+
+        let mut fwd = Forwarded::new();
+        assert!(fwd.is_empty());
+        fwd.push(elem.clone());
+
+        assert_eq!(
+            fwd.to_string(),
+            r#"for="192.0.2.60:4711";by=_proxy;host=rust-lang.org;proto=https"#,
+        );
+        assert_eq!(
+            Forwarded::parse(&fwd.try_into_value().unwrap())
+                .unwrap()
+                .into_elements(),
+            vec![elem],
+        );
+
+        // bare IPv6 nodes are bracketed
+        let mut elem = ForwardedElement::default();
+        elem.set_for_node("2001:db8::17");
+        elem.set_by("[2001:db8::1]");
+        assert_eq!(elem.for_node(), Some("[2001:db8::17]"));
+        assert_eq!(elem.by(), Some("[2001:db8::1]"));
+        assert_eq!(
+            elem.to_string(),
+            r#"for="[2001:db8::17]";by="[2001:db8::1]""#
+        );
+    }
+}
@@ -1,2 +1,5 @@
+pub(crate) mod forwarded;
 pub mod header;
+
+pub use self::forwarded::{Forwarded, ForwardedElement};
 pub use actix_http::http::*;
@@ -0,0 +1,202 @@
+//! Parsing core for the `Forwarded` header.
+//!
+//! Only depends on `core` and operates on plain string slices, without any request types, so it
+//! can be reused by components that do not use the rest of actix-web.
+
+/// Trim whitespace then any quote marks.
+pub(crate) fn unquote(val: &str) -> &str {
+    val.trim().trim_start_matches('"').trim_end_matches('"')
+}
+
+/// Parses a `Forwarded` header value into its name-value pairs, in order.
+///
+/// Pairs of all elements are returned as a single sequence; names are trimmed but values are
+/// returned as sent, including any quote marks. Commas and semicolons within quoted values do not
+/// separate elements or pairs. Pairs without a `=` or with empty values are skipped. Values are
+/// not interpreted.
+///
+/// # Examples
+/// ```
+/// use actix_web::dev::parse_forwarded;
+///
+/// let pairs = parse_forwarded(r#"for=192.0.2.60;proto=http, for="[2001:db8::1]""#)
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     pairs,
+///     vec![("for", "192.0.2.60"), ("proto", "http"), ("for", r#""[2001:db8::1]""#)],
+/// );
+/// ```
+pub fn parse_forwarded(input: &str) -> impl Iterator<Item = (&str, &str)> {
+    // "for=1.2.3.4, for=5.6.7.8; scheme=https" => ["for=1.2.3.4", " for=5.6.7.8; scheme=https"]
+    split_elements(input).flat_map(parse_element)
+}
+
+/// Elements of a `Forwarded` header value, as sent. Commas within quoted values do not separate
+/// elements.
+pub(crate) fn split_elements(input: &str) -> impl Iterator<Item = &str> {
+    split_unquoted(input, b',')
+}
+
+/// Name-value pairs of a single `Forwarded` element. Pairs with empty values are skipped.
+pub(crate) fn parse_element(elem: &str) -> impl Iterator<Item = (&str, &str)> {
+    // " for=5.6.7.8; scheme=https"
+    split_unquoted(elem, b';')
+        // [" for=5.6.7.8", " scheme=https"]
+        .map(str::trim)
+        // trailing or doubled delimiters, eg. "for=1.2.3.4;;proto=https;", yield empty pairs
+        .filter(|pair| !pair.is_empty())
+        .flat_map(|pair| {
+            // only the first `=` separates name and value; quoted values may contain more
+            let mut items = pair.splitn(2, '=');
+            // names are compared without allocating; the header is entirely client controlled
+            Some((items.next()?.trim(), items.next()?))
+        })
+        // [(name , val      ), ...                ]
+        // [("for", "5.6.7.8"), ("scheme", "https")]
+        .filter(|(_, val)| !unquote(val).is_empty())
+}
+
+/// Splits `input` on an ASCII delimiter that is not inside a quoted string.
+///
+/// Within quoted strings, a backslash escapes the next character (RFC 7230 §3.2.6), so `"a\",b"`
+/// is a single value. When a quoted string is left unterminated, the part instead ends at the next
+/// delimiter regardless of quotes, so a client-sent stray quote cannot swallow elements appended
+/// by proxies.
+fn split_unquoted(input: &str, delim: u8) -> impl Iterator<Item = &str> {
+    let mut rest = Some(input);
+
+    core::iter::from_fn(move || {
+        let input = rest?;
+        let mut quoted = false;
+        let mut escaped = false;
+
+        for (idx, b) in input.bytes().enumerate() {
+            if escaped {
+                escaped = false;
+            } else if quoted && b == b'\\' {
+                escaped = true;
+            } else if b == b'"' {
+                quoted = !quoted;
+            } else if b == delim && !quoted {
+                // the delimiter is ASCII so the index is a char boundary
+                rest = Some(&input[idx + 1..]);
+                return Some(&input[..idx]);
+            }
+        }
+
+        if quoted {
+            if let Some(idx) = input.bytes().position(|b| b == delim) {
+                rest = Some(&input[idx + 1..]);
+                return Some(&input[..idx]);
+            }
+        }
+
+        rest = None;
+        Some(input)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(input: &str) -> Vec<(&str, &str)> {
+        parse_forwarded(input).collect()
+    }
+
+    #[test]
+    fn unquote_values() {
+        assert_eq!(unquote(r#""192.0.2.60:4711""#), "192.0.2.60:4711");
+        assert_eq!(unquote(r#"  "[2001:db8::1]"  "#), "[2001:db8::1]");
+        assert_eq!(unquote("https"), "https");
+        assert_eq!(unquote(r#""""#), "");
+    }
+
+    #[test]
+    fn single_element() {
+        assert_eq!(
+            pairs("for=192.0.2.60; proto=https; host=rust-lang.org"),
+            vec![
+                ("for", "192.0.2.60"),
+                ("proto", "https"),
+                ("host", "rust-lang.org")
+            ],
+        );
+    }
+
+    #[test]
+    fn multiple_elements() {
+        assert_eq!(
+            pairs("for=192.0.2.60;by=_edge, for=_edge;by=_lb, proto=https"),
+            vec![
+                ("for", "192.0.2.60"),
+                ("by", "_edge"),
+                ("for", "_edge"),
+                ("by", "_lb"),
+                ("proto", "https"),
+            ],
+        );
+    }
+
+    #[test]
+    fn names_trimmed_case_preserved() {
+        assert_eq!(
+            pairs(" For =192.0.2.60 ;  PROTO=https"),
+            vec![("For", "192.0.2.60"), ("PROTO", "https")],
+        );
+    }
+
+    #[test]
+    fn quoted_values() {
+        assert_eq!(
+            pairs(r#"for="[2001:db8:cafe::17]:4711";host="rust-lang.org""#),
+            vec![
+                ("for", r#""[2001:db8:cafe::17]:4711""#),
+                ("host", r#""rust-lang.org""#)
+            ],
+        );
+
+        // only the first `=` separates the name
+        assert_eq!(pairs(r#"for="a=b""#), vec![("for", r#""a=b""#)]);
+
+        // delimiters within quoted values, including after escaped quotes, do not split
+        assert_eq!(
+            pairs(r#"for="a,b";host="c;d", for="e\",f""#),
+            vec![
+                ("for", r#""a,b""#),
+                ("host", r#""c;d""#),
+                ("for", r#""e\",f""#)
+            ],
+        );
+    }
+
+    #[test]
+    fn unterminated_quote() {
+        // a stray quote does not swallow the elements that follow
+        assert_eq!(
+            pairs(r#"for="1.2.3.4, for=5.6.7.8"#),
+            vec![("for", r#""1.2.3.4"#), ("for", "5.6.7.8")],
+        );
+        assert_eq!(
+            pairs(r#"for="1.2.3.4;proto=http, for="5.6.7.8""#),
+            vec![
+                ("for", r#""1.2.3.4"#),
+                ("proto", "http"),
+                ("for", r#""5.6.7.8""#)
+            ],
+        );
+        assert_eq!(pairs(r#"for="1.2.3.4"#), vec![("for", r#""1.2.3.4"#)]);
+    }
+
+    #[test]
+    fn empty_and_malformed_pairs() {
+        assert_eq!(
+            pairs("for=192.0.2.60;;proto=https;"),
+            vec![("for", "192.0.2.60"), ("proto", "https")],
+        );
+        assert_eq!(pairs("for=, proto=\"\", host"), vec![]);
+        assert_eq!(pairs(" , ;"), vec![]);
+        assert_eq!(pairs(""), vec![]);
+    }
+}
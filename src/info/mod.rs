@@ -0,0 +1,8066 @@
+use std::{
+    any::type_name,
+    borrow::Cow,
+    cell::Ref,
+    convert::Infallible,
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    ops::Deref,
+    rc::Rc,
+    str,
+    sync::Arc,
+    time::Instant,
+};
+
+use actix_utils::future::{err, ok, Ready};
+use derive_more::{Display, Error};
+#[cfg(feature = "reverse-dns")]
+use futures_core::future::LocalBoxFuture;
+use ipnet::IpNet;
+use once_cell::{
+    sync::{Lazy, OnceCell},
+    unsync,
+};
+use serde::Serialize;
+
+use crate::{
+    dev::{AppConfig, Extensions, Payload, RequestHead},
+    error::{Error, ErrorInternalServerError},
+    http::{
+        forwarded::parse_elements,
+        header::{self, HeaderName, HeaderValue},
+        uri::{PathAndQuery, Scheme},
+        ForwardedElement, Method, StatusCode, Uri, Version,
+    },
+    web::Data,
+    FromRequest, HttpRequest, ResponseError,
+};
+
+pub(crate) mod forwarded;
+
+pub use self::forwarded::parse_forwarded;
+use self::forwarded::unquote;
+
+static X_FORWARDED_FOR: Lazy<HeaderName> =
+    Lazy::new(|| HeaderName::from_static("x-forwarded-for"));
+static X_FORWARDED_HOST: Lazy<HeaderName> =
+    Lazy::new(|| HeaderName::from_static("x-forwarded-host"));
+static X_FORWARDED_PROTO: Lazy<HeaderName> =
+    Lazy::new(|| HeaderName::from_static("x-forwarded-proto"));
+static X_FORWARDED_SCHEME: Lazy<HeaderName> =
+    Lazy::new(|| HeaderName::from_static("x-forwarded-scheme"));
+static X_FORWARDED_SSL: Lazy<HeaderName> =
+    Lazy::new(|| HeaderName::from_static("x-forwarded-ssl"));
+static X_REAL_IP: Lazy<HeaderName> = Lazy::new(|| HeaderName::from_static("x-real-ip"));
+static X_FORWARDED_PORT: Lazy<HeaderName> =
+    Lazy::new(|| HeaderName::from_static("x-forwarded-port"));
+static LOOPBACK_NETWORKS: Lazy<[IpNet; 2]> =
+    Lazy::new(|| ["127.0.0.0/8".parse().unwrap(), "::1/128".parse().unwrap()]);
+static X_ENVOY_EXTERNAL_ADDRESS: Lazy<HeaderName> =
+    Lazy::new(|| HeaderName::from_static("x-envoy-external-address"));
+static X_ENVOY_INTERNAL: Lazy<HeaderName> =
+    Lazy::new(|| HeaderName::from_static("x-envoy-internal"));
+
+/// Maximum number of forwarded hops retained by [`ConnectionInfo`] per list; the hops nearest
+/// to this server are kept since the ones beyond them are the easiest for clients to forge.
+const MAX_RETAINED_HOPS: usize = 64;
+
+/// The last `MAX_RETAINED_HOPS` items of a list.
+fn retained_hops<T>(hops: &[T]) -> &[T] {
+    &hops[hops.len().saturating_sub(MAX_RETAINED_HOPS)..]
+}
+
+/// Decodes header value as UTF-8, allowing internationalized host names through unlike
+/// `HeaderValue::to_str`.
+fn header_str(hdr: &HeaderValue) -> Option<&str> {
+    str::from_utf8(hdr.as_bytes()).ok()
+}
+
+/// Extracts and trims first value of a header.
+fn first_value(hdr: &HeaderValue) -> Option<&str> {
+    let val = header_str(hdr)?.split(',').next()?.trim();
+    Some(val)
+}
+
+/// Extracts and trims first value for given header name.
+fn first_header_value<'a>(req: &'a RequestHead, name: &'_ HeaderName) -> Option<&'a str> {
+    first_value(req.headers.get(name)?)
+}
+
+/// Returns true if value is the `unknown` token or an [obfuscated identifier][rfc7239-63].
+///
+/// [rfc7239-63]: https://datatracker.ietf.org/doc/html/rfc7239#section-6.3
+fn is_obfuscated(val: &str) -> bool {
+    val.eq_ignore_ascii_case("unknown") || val.starts_with('_')
+}
+
+/// Returns true if `Forwarded` headers are present but none contain an element.
+fn forwarded_is_empty(req: &RequestHead) -> bool {
+    let mut values = req
+        .headers
+        .get_all(&header::FORWARDED)
+        .into_iter()
+        .peekable();
+
+    values.peek().is_some() && values.all(|hdr| parse_elements(hdr).next().is_none())
+}
+
+/// Elements of all `Forwarded` headers, each as sent and parsed, in order.
+fn forwarded_header_elements(
+    req: &RequestHead,
+) -> impl Iterator<Item = (&str, ForwardedElement)> {
+    req.headers
+        .get_all(&header::FORWARDED)
+        .into_iter()
+        .flat_map(parse_elements)
+}
+
+/// Returns true if a `Forwarded` `for` node is an IPv6 address without the brackets required by
+/// RFC 7239, eg. `for=2001:db8::1`.
+fn is_unbracketed_ipv6(node: &str) -> bool {
+    unquote(node).parse::<Ipv6Addr>().is_ok()
+}
+
+/// IP address found at a JSON pointer within a header's JSON value, eg. `/client/ip`.
+#[cfg(feature = "json-realip")]
+fn json_header_ip(req: &RequestHead, name: &HeaderName, pointer: &str) -> Option<IpAddr> {
+    let hdr = req.headers.get(name)?;
+    let json = serde_json::from_slice::<serde_json::Value>(hdr.as_bytes()).ok()?;
+    parse_node_ip(json.pointer(pointer)?.as_str()?)
+}
+
+/// Connection metadata sent by an edge proxy as Base64-encoded JSON, eg.
+/// `{"scheme": "https", "host": "example.com", "client_ip": "192.0.2.60"}`.
+#[cfg(feature = "edge-metadata")]
+#[derive(Debug, Default, serde::Deserialize)]
+struct EdgeMetadata {
+    scheme: Option<String>,
+    host: Option<String>,
+    client_ip: Option<String>,
+}
+
+#[cfg(feature = "edge-metadata")]
+impl EdgeMetadata {
+    /// Decodes the first value of the header, returning `None` if it is missing or malformed.
+    fn from_header(req: &RequestHead, name: &HeaderName) -> Option<Self> {
+        let json = base64::decode(first_header_value(req, name)?).ok()?;
+
+        serde_json::from_slice(&json)
+            .map_err(|err| log::debug!("malformed edge metadata header: {}", err))
+            .ok()
+    }
+}
+
+/// The IP address part of a node, without quotes, brackets or port, if it is an IP address.
+#[cfg(feature = "otel-attributes")]
+fn node_ip_str(node: &str) -> Option<&str> {
+    let node = unquote(node);
+    if node.parse::<IpAddr>().is_ok() {
+        return Some(node);
+    }
+
+    let (host, _) = split_host_port(node);
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    host.parse::<IpAddr>().ok().map(|_| host)
+}
+
+/// Splits the port from a host value, eg. `example.com:8080` or `[::1]:8080`.
+fn split_host_port(host: &str) -> (&str, Option<&str>) {
+    match host.rfind(':') {
+        // a colon inside brackets or in an unbracketed IPv6 address is not a port separator
+        Some(idx)
+            if !host[idx..].contains(']')
+                && (host.starts_with('[') || !host[..idx].contains(':')) =>
+        {
+            (&host[..idx], Some(&host[idx + 1..]))
+        }
+        _ => (host, None),
+    }
+}
+
+/// Strips a single trailing dot from a fully qualified host name, keeping any port, eg.
+/// `example.com.:8080` becomes `example.com:8080`. Returns `None` if there is no trailing dot.
+fn strip_fqdn_dot(host: &str) -> Option<String> {
+    let (hostname, port) = split_host_port(host);
+    let hostname = hostname.strip_suffix('.').filter(|name| !name.is_empty())?;
+
+    Some(match port {
+        Some(port) => format!("{}:{}", hostname, port),
+        None => hostname.to_owned(),
+    })
+}
+
+/// Default port for a URI scheme.
+fn default_port(scheme: &str) -> Option<u16> {
+    if scheme.eq_ignore_ascii_case("http") || scheme.eq_ignore_ascii_case("ws") {
+        Some(80)
+    } else if scheme.eq_ignore_ascii_case("https") || scheme.eq_ignore_ascii_case("wss") {
+        Some(443)
+    } else {
+        None
+    }
+}
+
+/// Converts IPv4-mapped IPv6 addresses to IPv4 so both forms compare equal.
+fn canonical_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => match v6.octets() {
+            [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, a, b, c, d] => {
+                IpAddr::V4(Ipv4Addr::new(a, b, c, d))
+            }
+            _ => ip,
+        },
+        IpAddr::V4(_) => ip,
+    }
+}
+
+/// Returns true if the address is publicly routable.
+///
+/// Private, shared (carrier-grade NAT), loopback, link-local, documentation, multicast and other
+/// special-purpose ranges are not public.
+fn is_public_ip(ip: IpAddr) -> bool {
+    match canonical_ip(ip) {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_unspecified()
+                || v4.is_multicast()
+                // 0.0.0.0/8, 100.64.0.0/10 (shared address space) and 240.0.0.0/4 (reserved)
+                || a == 0
+                || (a == 100 && b & 0xc0 == 64)
+                || a >= 240)
+        }
+        IpAddr::V6(v6) => {
+            let [a, b, ..] = v6.segments();
+
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                // fc00::/7 (unique local), fe80::/10 (link-local) and 2001:db8::/32 (documentation)
+                || a & 0xfe00 == 0xfc00
+                || a & 0xffc0 == 0xfe80
+                || (a == 0x2001 && b == 0x0db8))
+        }
+    }
+}
+
+/// Zeroes the host part of an address; keeps the /24 network for IPv4 and /48 for IPv6.
+fn anonymize_ip(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, 0))
+        }
+        IpAddr::V6(v6) => {
+            let [a, b, c, ..] = v6.segments();
+            IpAddr::V6(Ipv6Addr::new(a, b, c, 0, 0, 0, 0, 0))
+        }
+    }
+}
+
+/// Anonymized form of a node that is an IP address, without any port.
+fn anonymize_node(node: &str) -> Option<String> {
+    parse_node_ip(node).map(|ip| anonymize_ip(ip).to_string())
+}
+
+/// Anonymized form of a node that is an IP address; other nodes are kept as sent.
+fn anonymize_or_keep(node: &str) -> String {
+    anonymize_node(node).unwrap_or_else(|| node.to_owned())
+}
+
+/// Masks the `for` and `by` nodes of a raw `Forwarded` element that are IP addresses; other
+/// parameters are kept as sent.
+fn anonymize_forwarded_element(elem: &str) -> String {
+    forwarded::parse_element(elem)
+        .map(|(name, val)| {
+            let is_node = name.eq_ignore_ascii_case("for") || name.eq_ignore_ascii_case("by");
+
+            match parse_node_ip(val).filter(|_| is_node).map(anonymize_ip) {
+                Some(IpAddr::V4(ip)) => format!("{}={}", name, ip),
+                Some(IpAddr::V6(ip)) => format!("{}=\"[{}]\"", name, ip),
+                None => format!("{}={}", name, val),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// 64-bit FNV-1a. Unlike the std hasher, results are stable across processes and releases.
+fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Parses the IP address out of a node value, eg. `192.0.2.60`, `192.0.2.60:8080`, `[::1]` or
+/// `[2001:db8:cafe::17]:4711`.
+fn parse_node_ip(node: &str) -> Option<IpAddr> {
+    let node = unquote(node);
+
+    node.parse::<IpAddr>()
+        .or_else(|_| node.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+        .or_else(|| node.strip_prefix('[')?.strip_suffix(']')?.parse().ok())
+}
+
+/// Creates a span recording the candidate values from each resolution source.
+///
+/// Final values are recorded into the `scheme`, `host` and `realip` fields by the caller. Candidate
+/// values are only computed when the span is enabled; client address candidates are left out when
+/// `anonymize` is set.
+#[cfg(feature = "tracing")]
+fn resolution_span(
+    req: &RequestHead,
+    cfg: &AppConfig,
+    forwarded_proto: Option<&str>,
+    forwarded_host: Option<&str>,
+    forwarded_for: Option<&str>,
+    anonymize: bool,
+) -> tracing::Span {
+    use tracing::field::Empty;
+
+    tracing::trace_span!(
+        "connection_info",
+        forwarded.proto = forwarded_proto,
+        x_forwarded_proto = first_header_value(req, &*X_FORWARDED_PROTO),
+        x_forwarded_scheme = first_header_value(req, &*X_FORWARDED_SCHEME),
+        x_forwarded_ssl = first_header_value(req, &*X_FORWARDED_SSL),
+        uri.scheme = req.uri.scheme_str(),
+        app_config.secure = cfg.secure(),
+        scheme = Empty,
+        forwarded.host = forwarded_host,
+        x_forwarded_host = first_header_value(req, &*X_FORWARDED_HOST),
+        host_header = req.headers.get(&header::HOST).and_then(header_str),
+        uri.authority = req.uri.authority().map(|authority| authority.as_str()),
+        app_config.host = cfg.host(),
+        host = Empty,
+        forwarded.r#for = forwarded_for.filter(|_| !anonymize),
+        x_forwarded_for = first_header_value(req, &*X_FORWARDED_FOR).filter(|_| !anonymize),
+        realip = Empty,
+    )
+}
+
+/// HTTP connection information.
+///
+/// `ConnectionInfo` implements `FromRequest` and can be extracted in handlers.
+///
+/// # Examples
+/// ```
+/// # use actix_web::{HttpResponse, Responder};
+/// use actix_web::dev::ConnectionInfo;
+///
+/// async fn handler(conn: ConnectionInfo) -> impl Responder {
+///     match conn.host() {
+///         "actix.rs" => HttpResponse::Ok().body("Welcome!"),
+///         "admin.actix.rs" => HttpResponse::Ok().body("Admin portal."),
+///         _ => HttpResponse::NotFound().finish()
+///     }
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+///
+/// # Implementation Notes
+/// Parses `Forwarded` header information according to [RFC 7239][rfc7239] but does not try to
+/// interpret the values for each property. As such, the getter methods on `ConnectionInfo` return
+/// strings instead of IP addresses or other types to acknowledge that they may be
+/// [obfuscated][rfc7239-63] or [unknown][rfc7239-62].
+///
+/// If the older, related headers are also present (eg. `X-Forwarded-For`), then `Forwarded`
+/// is preferred.
+///
+/// Inputs that are constant for a connection, such as [`TlsInfo`], [`Transport`] and other
+/// connection data, are collected once when [`HttpServer`](crate::HttpServer) accepts the
+/// connection and shared by all requests on it. Only the headers are parsed per request, once,
+/// after which the result is cached in the request extensions. Headers behind rarely used values,
+/// eg. [`via_chain`](Self::via_chain) and [`proxy_path`](Self::proxy_path), are only parsed when
+/// those are first accessed.
+///
+/// [rfc7239]: https://datatracker.ietf.org/doc/html/rfc7239
+/// [rfc7239-62]: https://datatracker.ietf.org/doc/html/rfc7239#section-6.2
+/// [rfc7239-63]: https://datatracker.ietf.org/doc/html/rfc7239#section-6.3
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionInfo {
+    scheme: String,
+    scheme_source: SchemeSource,
+    host_source: HostSource,
+    realip_source: RealIpSource,
+    trusted_proxy: Option<IpNet>,
+    secure_transport: bool,
+    trusted_forwarding: bool,
+    host: String,
+    raw_host: Option<String>,
+    realip_remote_addr: Option<String>,
+    remote_addr: Option<String>,
+    peer_addr: Option<SocketAddr>,
+    forwarded_for: Vec<String>,
+    hop_count: usize,
+    forwarded_port: Option<u16>,
+    forwarded_client_port: Option<u16>,
+    established_at: Option<Instant>,
+    http2_negotiation: Option<Http2Negotiation>,
+    transport: Transport,
+    forwarding_headers: ForwardingHeadersPresent,
+    lazy: LazyFields,
+    #[cfg(feature = "reverse-dns")]
+    resolver: Option<SharedResolver>,
+    #[cfg(feature = "registrable-domain")]
+    suffix_list: Option<SharedSuffixList>,
+}
+
+/// Header values behind the rarely used fields of [`ConnectionInfo`].
+///
+/// Keeping a header value only bumps a reference count, so resolution does not parse or copy
+/// them; each field is parsed on first access and then cached. Values of forwarding headers that
+/// were not honored are not kept.
+#[derive(Debug, Clone, Default)]
+struct LazyFields {
+    forwarded: Vec<HeaderValue>,
+    x_forwarded_for: Vec<HeaderValue>,
+    via: Vec<HeaderValue>,
+    client_hints: Vec<(HeaderName, HeaderValue)>,
+    country_code: Option<HeaderValue>,
+    anonymized: bool,
+    proxy_path: OnceCell<Vec<(Option<String>, Option<String>)>>,
+    forwarded_elements: OnceCell<Vec<String>>,
+    x_forwarded_for_list: OnceCell<Option<String>>,
+    via_chain: OnceCell<Vec<ViaHop>>,
+}
+
+impl LazyFields {
+    fn new(
+        req: &RequestHead,
+        info_cfg: &ConnectionInfoConfig,
+        honor_forwarding: bool,
+        use_forwarded: bool,
+    ) -> Self {
+        let values = |name: &HeaderName| req.headers.get_all(name).cloned().collect::<Vec<_>>();
+
+        LazyFields {
+            forwarded: if use_forwarded && honor_forwarding {
+                values(&header::FORWARDED)
+            } else {
+                Vec::new()
+            },
+            x_forwarded_for: if honor_forwarding {
+                values(&*X_FORWARDED_FOR)
+            } else {
+                Vec::new()
+            },
+            via: values(&header::VIA),
+            client_hints: req
+                .headers
+                .iter()
+                .filter(|(name, _)| name.as_str().starts_with("sec-ch-"))
+                .map(|(name, val)| (name.clone(), val.clone()))
+                .collect(),
+            country_code: info_cfg
+                .geo_header
+                .as_ref()
+                .and_then(|name| req.headers.get(name))
+                .cloned(),
+            ..LazyFields::default()
+        }
+    }
+
+    // the lists below are capped so huge forwarding headers cannot balloon the cached info
+
+    fn proxy_path(&self) -> &[(Option<String>, Option<String>)] {
+        self.proxy_path.get_or_init(|| {
+            let mask = |node: &str| {
+                if self.anonymized {
+                    anonymize_or_keep(node)
+                } else {
+                    node.to_owned()
+                }
+            };
+
+            let hops = self
+                .forwarded
+                .iter()
+                .flat_map(parse_elements)
+                .map(|(_, elem)| elem)
+                // elements without either node, eg. "proto=https", do not describe a hop
+                .filter(|elem| elem.by().is_some() || elem.for_node().is_some())
+                .collect::<Vec<_>>();
+
+            retained_hops(&hops)
+                .iter()
+                .map(|elem| (elem.by().map(mask), elem.for_node().map(mask)))
+                .collect()
+        })
+    }
+
+    fn forwarded_elements(&self) -> &[String] {
+        self.forwarded_elements.get_or_init(|| {
+            // raw elements are kept, rather than re-serialized, so extension parameters are
+            // passed on
+            let elements = self
+                .forwarded
+                .iter()
+                .flat_map(parse_elements)
+                .map(|(raw, _)| raw)
+                .collect::<Vec<_>>();
+
+            retained_hops(&elements)
+                .iter()
+                .map(|elem| {
+                    if self.anonymized {
+                        anonymize_forwarded_element(elem)
+                    } else {
+                        (*elem).to_owned()
+                    }
+                })
+                .collect()
+        })
+    }
+
+    fn x_forwarded_for(&self) -> Option<&str> {
+        self.x_forwarded_for_list
+            .get_or_init(|| {
+                let nodes = self
+                    .x_forwarded_for
+                    .iter()
+                    .filter_map(header_str)
+                    .flat_map(|val| val.split(','))
+                    .map(str::trim)
+                    .filter(|node| !node.is_empty())
+                    .collect::<Vec<_>>();
+
+                match retained_hops(&nodes) {
+                    [] => None,
+                    nodes if self.anonymized => Some(
+                        nodes
+                            .iter()
+                            .map(|node| anonymize_or_keep(node))
+                            .collect::<Vec<_>>()
+                            .join(", "),
+                    ),
+                    nodes => Some(nodes.join(", ")),
+                }
+            })
+            .as_deref()
+    }
+
+    fn via_chain(&self) -> &[ViaHop] {
+        self.via_chain.get_or_init(|| {
+            self.via
+                .iter()
+                .filter_map(|hdr| hdr.to_str().ok())
+                // "1.1 vegur, 1.0 fred (Apache/1.1)"
+                .flat_map(|val| val.split(','))
+                .filter_map(|hop| {
+                    let mut parts = hop.split_whitespace();
+                    let protocol = parts.next()?.to_owned();
+                    let received_by = parts.next()?;
+
+                    Some(ViaHop {
+                        protocol,
+                        received_by: if self.anonymized {
+                            anonymize_or_keep(received_by)
+                        } else {
+                            received_by.to_owned()
+                        },
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn client_hint(&self, name: &str) -> Option<&str> {
+        self.client_hints
+            .iter()
+            .filter(|(hint, _)| hint.as_str().eq_ignore_ascii_case(name))
+            .find_map(|(_, val)| header_str(val))
+    }
+
+    fn country_code(&self) -> Option<&str> {
+        self.country_code
+            .as_ref()
+            .and_then(first_value)
+            .filter(|code| !code.is_empty())
+    }
+
+    /// Parses the address-bearing fields with their addresses masked and drops the raw values,
+    /// which would otherwise keep the full addresses.
+    fn anonymize(&mut self) {
+        if self.anonymized {
+            return;
+        }
+
+        self.anonymized = true;
+
+        // fields already parsed were not masked
+        self.proxy_path = OnceCell::new();
+        self.forwarded_elements = OnceCell::new();
+        self.x_forwarded_for_list = OnceCell::new();
+        self.via_chain = OnceCell::new();
+
+        self.proxy_path();
+        self.forwarded_elements();
+        self.x_forwarded_for();
+        self.via_chain();
+
+        self.forwarded.clear();
+        self.x_forwarded_for.clear();
+        self.via.clear();
+    }
+}
+
+impl ConnectionInfo {
+    /// Create *ConnectionInfo* instance for a request.
+    ///
+    /// Returns the info already resolved by [`HttpRequest::connection_info`] if there is one.
+    /// Otherwise the info is resolved with the default [`ConnectionInfoConfig`], since the request
+    /// head does not give access to app data; that result is cached separately, so a later call
+    /// to `HttpRequest::connection_info` still applies the configuration set in app data, including
+    /// a custom [resolver](ConnectionInfoConfig::connection_info_resolver). Prefer
+    /// `HttpRequest::connection_info` where a request is available.
+    pub fn get<'a>(req: &'a RequestHead, cfg: &AppConfig) -> Ref<'a, Self> {
+        {
+            let extensions = req.extensions();
+            if extensions.contains::<ConnectionInfo>() {
+                return Ref::map(extensions, |e| e.get().unwrap());
+            }
+            if extensions.contains::<DefaultConnectionInfo>() {
+                return Ref::map(extensions, |e| {
+                    let default = e.get::<DefaultConnectionInfo>().unwrap();
+                    default.configured.get().unwrap_or(&default.info)
+                });
+            }
+        }
+
+        let info = ConnectionInfo::new(req, cfg, &DEFAULT_CONFIG);
+        req.extensions_mut().insert(DefaultConnectionInfo {
+            info,
+            configured: unsync::OnceCell::new(),
+        });
+
+        Ref::map(req.extensions(), |e| {
+            &e.get::<DefaultConnectionInfo>().unwrap().info
+        })
+    }
+
+    /// Returns the cached info for this request head, resolving it on first use.
+    ///
+    /// The cached entry doubles as the "already resolved" marker so `new` runs at
+    /// most once per request head, no matter how many extractors or middleware ask for it.
+    pub(crate) fn get_with_config<'a>(
+        req: &'a RequestHead,
+        cfg: &AppConfig,
+        info_cfg: &ConnectionInfoConfig,
+    ) -> Ref<'a, Self> {
+        // fast path; cached info is returned under the single shared borrow
+        {
+            let extensions = req.extensions();
+            if extensions.contains::<ConnectionInfo>() {
+                return Ref::map(extensions, |e| e.get().unwrap());
+            }
+            if let Some(default) = extensions.get::<DefaultConnectionInfo>() {
+                if default.configured.get().is_some() {
+                    return Ref::map(extensions, |e| {
+                        e.get::<DefaultConnectionInfo>()
+                            .unwrap()
+                            .configured
+                            .get()
+                            .unwrap()
+                    });
+                }
+            }
+        }
+
+        // resolve before taking the mutable borrow since connection data is read from the
+        // same extensions container
+        let info = match ConnectionInfo::resolve(req, cfg, info_cfg).try_insert_cached(req) {
+            Ok(()) => return Ref::map(req.extensions(), |e| e.get().unwrap()),
+            Err(info) => info,
+        };
+
+        // the extensions are still borrowed, eg. through a `Ref` returned by `get`; the info is
+        // kept next to the default-config info, which is all such a borrow can point to
+        let extensions = req.extensions();
+        match extensions.get::<DefaultConnectionInfo>() {
+            Some(default) => {
+                let _ = default.configured.set(info);
+            }
+            None => {
+                // borrowed by the caller rather than through `get`; there is nowhere to keep
+                // the info so this behaves as inserting into borrowed extensions always has
+                drop(extensions);
+                info.insert_cached(req);
+                return Ref::map(req.extensions(), |e| e.get().unwrap());
+            }
+        }
+
+        Ref::map(extensions, |e| {
+            e.get::<DefaultConnectionInfo>()
+                .unwrap()
+                .configured
+                .get()
+                .unwrap()
+        })
+    }
+
+    /// Resolves the info for a request head with the custom resolver, if any.
+    fn resolve(
+        req: &RequestHead,
+        cfg: &AppConfig,
+        info_cfg: &ConnectionInfoConfig,
+    ) -> ConnectionInfo {
+        match &info_cfg.info_resolver {
+            Some(resolver) => resolver.0.resolve(req, cfg),
+            None => ConnectionInfo::new(req, cfg, info_cfg),
+        }
+    }
+
+    /// Caches this info, and the client IP parsed from it, in the request extensions.
+    fn insert_cached(self, req: &RequestHead) {
+        self.cache_in(&mut req.extensions_mut());
+    }
+
+    /// Like `insert_cached` but hands the info back when the extensions are already borrowed.
+    fn try_insert_cached(self, req: &RequestHead) -> Result<(), Self> {
+        match req.extensions.try_borrow_mut() {
+            Ok(mut extensions) => {
+                self.cache_in(&mut extensions);
+                Ok(())
+            }
+            Err(_) => Err(self),
+        }
+    }
+
+    fn cache_in(self, extensions: &mut Extensions) {
+        // a client IP cached with previous info must not outlive it
+        match self.realip_remote_addr().and_then(parse_node_ip) {
+            Some(ip) => extensions.insert(ResolvedClientIp(ip)),
+            None => extensions.remove::<ResolvedClientIp>(),
+        };
+
+        extensions.insert(self);
+    }
+
+    /// Replaces the cached connection info of a request with this one.
+    ///
+    /// Connection info is resolved once per request and cached in the request extensions; all
+    /// later calls to [`HttpRequest::connection_info`] and the `ConnectionInfo` extractor return
+    /// the cached value. Replacing it from middleware, eg. after [`set_host`](Self::set_host), makes
+    /// downstream middleware and handlers see the modified info. Headers are not re-parsed; the
+    /// cached [`ResolvedClientIp`] request extension is updated to the new real IP, or removed if
+    /// it is not an IP address.
+    ///
+    /// The cache is borrowed by any outstanding `Ref` returned from
+    /// [`connection_info`](HttpRequest::connection_info); drop it before calling this method.
+    ///
+    /// # Errors
+    /// Returns [`ConnectionInfoBorrowed`] and leaves the cache unchanged if the request
+    /// extensions are currently borrowed.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_service::Service as _;
+    /// use actix_web::{dev::ConnectionInfo, App};
+    ///
+    /// let app = App::new().wrap_fn(|req, srv| {
+    ///     // canonicalize the host for all downstream extractors
+    ///     if req.connection_info().host() != "rust-lang.org" {
+    ///         let mut info = req.connection_info().clone();
+    ///         info.set_host("rust-lang.org");
+    ///         info.replace_cached(req.head()).expect("connection info is not borrowed");
+    ///     }
+    ///
+    ///     srv.call(req)
+    /// });
+    /// ```
+    pub fn replace_cached(self, req: &RequestHead) -> Result<(), ConnectionInfoBorrowed> {
+        self.try_insert_cached(req)
+            .map_err(|_| ConnectionInfoBorrowed)
+    }
+
+    fn new(
+        req: &RequestHead,
+        cfg: &AppConfig,
+        info_cfg: &ConnectionInfoConfig,
+    ) -> ConnectionInfo {
+        #[cfg(test)]
+        tests::PARSE_COUNT.with(|count| count.set(count.get() + 1));
+
+        // parsed elements are kept for the whole resolution; the chain borrows their nodes
+        let mut forwarded = Vec::new();
+        let mut host = None;
+        let mut scheme = None;
+        let mut for_chain = Vec::new();
+
+        let conn = ConnectionConstants::of(req);
+        let is_tls = cfg.secure() || conn.tls;
+        let transport_peer_addr = req.peer_addr.or(conn.quic_peer_addr);
+        let peer_addr = transport_peer_addr.or(info_cfg.synthetic_peer_addr);
+
+        // with trusted proxies configured, no forwarding header is honored from other peers, so
+        // untrusted clients cannot spoof the scheme or host either; a synthetic peer address
+        // stands in for any client, eg. every client of a Unix socket, so it is never trusted
+        let peer_trusted = match (&info_cfg.trusted_proxies, transport_peer_addr) {
+            (Some(trusted), Some(peer)) => trusted.network_of(peer.ip()).is_some(),
+            (Some(_), None) => false,
+            (None, _) => true,
+        };
+
+        let honor_forwarding = (is_tls || !info_cfg.require_tls_for_forwarding) && peer_trusted;
+        let forwarding_header =
+            |name: &HeaderName| first_header_value(req, name).filter(|_| honor_forwarding);
+
+        // fast path for the common case of no `Forwarded` header, eg. only `X-Forwarded-For`; the
+        // RFC 7239 parsing is skipped entirely
+        let has_forwarded = req.headers.contains_key(&header::FORWARDED);
+        let use_forwarded = has_forwarded && info_cfg.honor_forwarded_header;
+
+        if use_forwarded {
+            if forwarded_is_empty(req) {
+                log::debug!("Forwarded header has no elements; proxy may be misconfigured");
+            }
+
+            if honor_forwarding {
+                forwarded.extend(forwarded_header_elements(req).map(|(_, elem)| elem));
+            }
+        }
+
+        for elem in &forwarded {
+            // all "for" values are kept in order since spec states that first "for" value is
+            // client and rest are proxies; taking the first value for other properties is
+            // correct because multiple values have no defined semantics
+            //
+            // > In a chain of proxy servers where this is fully utilized, the first
+            // > "for" parameter will disclose the client where the request was first
+            // > made, followed by any subsequent proxy identifiers.
+            // --- https://datatracker.ietf.org/doc/html/rfc7239#section-5.2
+
+            if let Some(node) = elem.for_node() {
+                for_chain.push(node);
+            }
+
+            if scheme.is_none() {
+                scheme = elem.proto();
+            }
+
+            if host.is_none() {
+                host = elem.host().filter(|host| !is_obfuscated(host));
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        let span = resolution_span(
+            req,
+            cfg,
+            scheme,
+            host,
+            for_chain.first().copied(),
+            info_cfg.anonymize_ip,
+        );
+
+        #[cfg(feature = "edge-metadata")]
+        let edge_meta = info_cfg
+            .edge_metadata_header
+            .as_ref()
+            .filter(|_| honor_forwarding)
+            .and_then(|name| EdgeMetadata::from_header(req, name))
+            .unwrap_or_default();
+        #[cfg(feature = "edge-metadata")]
+        let (edge_scheme, edge_host) = (
+            edge_meta
+                .scheme
+                .as_deref()
+                .filter(|scheme| !scheme.is_empty())
+                .map(|scheme| (scheme, SchemeSource::EdgeMetadata)),
+            edge_meta
+                .host
+                .as_deref()
+                .filter(|host| !host.is_empty())
+                .map(|host| (host, HostSource::EdgeMetadata)),
+        );
+        #[cfg(not(feature = "edge-metadata"))]
+        let (edge_scheme, edge_host) = (None, None);
+
+        let (scheme, scheme_source) = edge_scheme
+            .or_else(|| scheme.map(|scheme| (scheme, SchemeSource::Forwarded)))
+            .or_else(|| {
+                forwarding_header(&*X_FORWARDED_PROTO)
+                    .map(|scheme| (scheme, SchemeSource::XForwardedProto))
+            })
+            .or_else(|| {
+                forwarding_header(&*X_FORWARDED_SCHEME)
+                    .map(|scheme| (scheme, SchemeSource::XForwardedScheme))
+            })
+            .or_else(|| {
+                forwarding_header(&*X_FORWARDED_SSL)
+                    .filter(|ssl| ssl.eq_ignore_ascii_case("on"))
+                    .map(|_| ("https", SchemeSource::XForwardedSsl))
+            })
+            .or_else(|| {
+                req.uri
+                    .scheme()
+                    .map(|scheme| (scheme.as_str(), SchemeSource::Uri))
+            })
+            .or_else(|| Some(("https", SchemeSource::Tls)).filter(|_| is_tls))
+            .unwrap_or((&info_cfg.default_scheme, SchemeSource::Default));
+        let scheme = scheme.to_owned();
+
+        let forwarded_host = host.map(|host| (host, HostSource::Forwarded));
+        let x_forwarded_host = || {
+            forwarding_header(&*X_FORWARDED_HOST).map(|host| (host, HostSource::XForwardedHost))
+        };
+
+        let uri_authority = || {
+            req.uri
+                .authority()
+                .map(|authority| (authority.as_str(), HostSource::Uri))
+        };
+
+        // in HTTP/1, the authority of an absolute-form request target replaces the Host header
+        // > When a proxy receives a request with an absolute-form of request-target, the proxy
+        // > MUST ignore the received Host header field (if any) and instead replace it with the
+        // > host information of the request-target.
+        // --- https://datatracker.ietf.org/doc/html/rfc7230#section-5.4
+        let is_absolute_form = req.version < Version::HTTP_2 && req.uri.authority().is_some();
+        let prefer_authority = is_absolute_form
+            || (info_cfg.prefer_http2_authority && req.version >= Version::HTTP_2);
+
+        let (host, host_source) = edge_host
+            .or_else(|| match info_cfg.host_preference {
+                HostPreference::Forwarded => forwarded_host.or_else(x_forwarded_host),
+                HostPreference::XForwardedHost => x_forwarded_host().or(forwarded_host),
+            })
+            .or_else(|| uri_authority().filter(|_| prefer_authority))
+            .or_else(|| {
+                header_str(req.headers.get(&header::HOST)?).map(|host| (host, HostSource::Host))
+            })
+            .or_else(uri_authority)
+            .map(|(host, source)| (host.to_owned(), source))
+            .or_else(|| {
+                // clients that omit both Host and `:authority` still name the server in the handshake
+                conn.sni
+                    .clone()
+                    .filter(|_| info_cfg.sni_host_fallback)
+                    .map(|sni| (sni, HostSource::Sni))
+            })
+            .unwrap_or_else(|| (cfg.host().to_owned(), HostSource::AppConfig));
+
+        let mut realip_source = RealIpSource::Forwarded;
+
+        // legacy header is only consulted when Forwarded provided no nodes
+        if for_chain.is_empty() && honor_forwarding {
+            realip_source = RealIpSource::XForwardedFor;
+
+            for_chain.extend(
+                req.headers
+                    .get_all(&*X_FORWARDED_FOR)
+                    .filter_map(header_str)
+                    // "1.2.3.4, 5.6.7.8"
+                    .flat_map(|val| val.split(','))
+                    .map(str::trim)
+                    .filter(|node| !node.is_empty()),
+            );
+        }
+
+        let mut trusted_proxy = None;
+
+        // some proxies send IPv6 `for` nodes unbracketed; they are normalized to the RFC 7239
+        // form so they are reported the same as compliant nodes
+        let from_forwarded = realip_source == RealIpSource::Forwarded;
+        let node_to_owned = |node: &str| {
+            if from_forwarded && is_unbracketed_ipv6(node) {
+                format!("[{}]", node)
+            } else {
+                node.to_owned()
+            }
+        };
+
+        let realip_headers_trusted = info_cfg.realip_resolver.is_none() && honor_forwarding;
+
+        #[cfg(feature = "json-realip")]
+        let json_realip = info_cfg
+            .realip_json_header
+            .as_ref()
+            .filter(|_| realip_headers_trusted)
+            .and_then(|(name, pointer)| json_header_ip(req, name, pointer))
+            .map(|ip| {
+                realip_source = RealIpSource::JsonHeader;
+                ip.to_string()
+            });
+        #[cfg(not(feature = "json-realip"))]
+        let json_realip = None;
+
+        #[cfg(feature = "edge-metadata")]
+        let edge_realip = edge_meta
+            .client_ip
+            .as_deref()
+            .filter(|_| realip_headers_trusted)
+            .and_then(parse_node_ip)
+            .map(|ip| {
+                realip_source = RealIpSource::EdgeMetadata;
+                ip.to_string()
+            });
+        #[cfg(not(feature = "edge-metadata"))]
+        let edge_realip = None;
+
+        // Envoy only sets the external address for requests from outside the mesh
+        let header_realip = json_realip.or(edge_realip).or_else(|| {
+            info_cfg
+                .envoy_external_address
+                .as_ref()
+                .filter(|_| realip_headers_trusted)
+                .filter(|_| {
+                    first_header_value(req, &*X_ENVOY_INTERNAL)
+                        .map_or(true, |internal| !internal.eq_ignore_ascii_case("true"))
+                })
+                .and_then(|name| parse_node_ip(first_header_value(req, name)?))
+                .map(|ip| {
+                    realip_source = RealIpSource::Envoy;
+                    ip.to_string()
+                })
+        });
+
+        // forwarded values are ignored unless the peer is trusted, in which case the real IP falls
+        // back to the peer address rather than a possibly spoofed header
+        let peer_ip = transport_peer_addr.map(|addr| addr.ip());
+        let chain_client = match &info_cfg.trusted_proxies {
+            Some(trusted) => trusted.client(peer_ip, &for_chain, &info_cfg.internal_node_names),
+            None => match info_cfg.realip_strategy {
+                RealIpStrategy::FirstHop => for_chain.first().copied(),
+                RealIpStrategy::LastHop => for_chain.last().copied(),
+            },
+        };
+
+        // taken before the real IP is resolved from other sources or anonymized
+        let forwarded_client_port = chain_client
+            .filter(|_| from_forwarded)
+            .and_then(|node| unquote(node).parse::<SocketAddr>().ok())
+            .map(|addr| addr.port());
+
+        let realip_remote_addr = header_realip.or_else(|| {
+            match (&info_cfg.realip_resolver, &info_cfg.trusted_proxies) {
+                (Some(resolver), _) => {
+                    realip_source = RealIpSource::Resolver;
+                    (resolver.0)(req).map(|ip| ip.to_string())
+                }
+                (None, Some(trusted)) => {
+                    trusted_proxy = peer_ip.and_then(|ip| trusted.network_of(ip)).copied();
+                    chain_client.map(node_to_owned)
+                }
+                (None, None) => chain_client.map(node_to_owned),
+            }
+        });
+
+        let remote_addr = peer_addr.map(|addr| addr.to_string());
+
+        // capped so huge forwarding headers cannot balloon the cached info
+        let hop_count = for_chain.len();
+        let forwarded_for = retained_hops(&for_chain)
+            .iter()
+            .map(|node| node_to_owned(node))
+            .collect::<Vec<_>>();
+
+        let forwarded_port =
+            forwarding_header(&*X_FORWARDED_PORT).and_then(|port| port.parse().ok());
+
+        let established_at = conn.established_at;
+
+        let forwarding_headers = ForwardingHeadersPresent {
+            forwarded: has_forwarded,
+            x_forwarded_for: req.headers.contains_key(&*X_FORWARDED_FOR),
+            x_forwarded_host: req.headers.contains_key(&*X_FORWARDED_HOST),
+            x_forwarded_proto: req.headers.contains_key(&*X_FORWARDED_PROTO),
+            x_real_ip: req.headers.contains_key(&*X_REAL_IP),
+        };
+
+        let transport = match conn.transport {
+            Some(transport) => transport,
+            None if req.peer_addr.is_some() => Transport::Tcp,
+            None if conn.quic_peer_addr.is_some() => Transport::Quic,
+            None => Transport::Unknown,
+        };
+
+        let http2_negotiation = if req.version == Version::HTTP_2 {
+            conn.http2_negotiation
+        } else {
+            None
+        };
+
+        // fully qualified names are equivalent to their relative form for routing
+        let (host, raw_host) = match strip_fqdn_dot(&host) {
+            Some(normalized) => (normalized, Some(host)),
+            None => (host, None),
+        };
+
+        let mut info = ConnectionInfo {
+            remote_addr,
+            scheme,
+            scheme_source,
+            host_source,
+            realip_source,
+            trusted_proxy,
+            secure_transport: cfg.secure(),
+            // without configured trusted proxies, forwarding headers are honored from anyone
+            trusted_forwarding: honor_forwarding && info_cfg.trusted_proxies.is_some(),
+            host,
+            raw_host,
+            realip_remote_addr,
+            peer_addr,
+            forwarded_for,
+            hop_count,
+            forwarded_port,
+            forwarded_client_port,
+            established_at,
+            http2_negotiation,
+            transport,
+            forwarding_headers,
+            lazy: LazyFields::new(req, info_cfg, honor_forwarding, use_forwarded),
+            #[cfg(feature = "reverse-dns")]
+            resolver: info_cfg.resolver.clone(),
+            #[cfg(feature = "registrable-domain")]
+            suffix_list: info_cfg.suffix_list.clone(),
+        };
+
+        // applied last so no source, including overrides, stores a full client address
+        if info_cfg.anonymize_ip {
+            info.anonymize_addresses();
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            span.record("scheme", info.scheme.as_str());
+            span.record("host", info.host.as_str());
+            span.record("realip", info.realip_remote_addr.as_deref());
+        }
+
+        info
+    }
+
+    /// Masks every stored client and proxy address.
+    ///
+    /// The real IP and peer address are removed if they are not IP addresses; nodes of the
+    /// forwarded chains that are not addresses, eg. obfuscated identifiers, are kept.
+    fn anonymize_addresses(&mut self) {
+        self.realip_remote_addr = self.realip_remote_addr.as_deref().and_then(anonymize_node);
+        self.remote_addr = self.remote_addr.as_deref().and_then(anonymize_node);
+        self.peer_addr = None;
+
+        for node in &mut self.forwarded_for {
+            *node = anonymize_or_keep(node);
+        }
+
+        self.lazy.anonymize();
+    }
+
+    /// Scheme of the request.
+    ///
+    /// Scheme is resolved through the following sources, in this order:
+    ///
+    /// - Forwarded
+    /// - X-Forwarded-Proto
+    /// - X-Forwarded-Scheme
+    /// - X-Forwarded-SSL (`on` only)
+    /// - Uri
+    /// - TLS connection, accepted by this server or recorded as [`TlsInfo`] connection data
+    #[inline]
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Sets the scheme, eg. in canonical-host middleware.
+    ///
+    /// [`scheme_source`](Self::scheme_source) becomes [`SchemeSource::Replaced`]. Only this value is
+    /// changed; use [`replace_cached`](Self::replace_cached) to make it visible to downstream
+    /// extractors.
+    pub fn set_scheme(&mut self, scheme: impl Into<String>) {
+        self.scheme = scheme.into();
+        self.scheme_source = SchemeSource::Replaced;
+    }
+
+    /// Returns true if the [scheme](Self::scheme) equals `other`, ignoring ASCII case.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default()
+    ///     .insert_header(("x-forwarded-proto", "HTTPS"))
+    ///     .to_http_request();
+    /// assert!(req.connection_info().scheme_eq("https"));
+    /// ```
+    #[inline]
+    pub fn scheme_eq(&self, other: &str) -> bool {
+        self.scheme.eq_ignore_ascii_case(other)
+    }
+
+    /// Returns true if the [scheme](Self::scheme) is secure, ie. `https` or `wss`.
+    ///
+    /// Unlike [`should_set_secure_cookies`](Self::should_set_secure_cookies), a secure scheme
+    /// counts whatever its source, including the client-controlled request URI.
+    #[inline]
+    pub fn is_secure(&self) -> bool {
+        self.scheme_eq("https") || self.scheme_eq("wss")
+    }
+
+    /// Where the [scheme](Self::scheme) was resolved from.
+    #[inline]
+    pub fn scheme_source(&self) -> SchemeSource {
+        self.scheme_source
+    }
+
+    /// Returns true if cookies issued in response to this request should use the `Secure`
+    /// attribute.
+    ///
+    /// Requires the resolved scheme to be secure (`https` or `wss`) and to be backed by a source
+    /// the client cannot set: either the connection uses TLS, accepted by this server or recorded
+    /// as [`TlsInfo`] connection data, or the scheme came from a forwarding header sent by a
+    /// configured [trusted proxy](ConnectionInfoConfig::trusted_proxies). Without trusted proxies,
+    /// forwarding headers are honored from every peer and so do not qualify; neither does the
+    /// client-controlled request URI.
+    pub fn should_set_secure_cookies(&self, req: &RequestHead) -> bool {
+        self.is_secure() && (self.is_tls(req) || self.scheme_from_trusted_proxy())
+    }
+
+    /// Returns true if the connection uses TLS, accepted by this server or recorded as
+    /// [`TlsInfo`] connection data.
+    fn is_tls(&self, req: &RequestHead) -> bool {
+        self.secure_transport || ConnectionConstants::of(req).tls
+    }
+
+    /// Returns true if the scheme came from a forwarding header sent by a configured trusted
+    /// proxy.
+    fn scheme_from_trusted_proxy(&self) -> bool {
+        self.trusted_forwarding && self.scheme_source.is_forwarding()
+    }
+
+    /// Hostname of the request.
+    ///
+    /// Hostname is resolved through the following headers, in this order:
+    ///
+    /// - Forwarded
+    /// - X-Forwarded-Host
+    /// - Host
+    /// - Uri
+    /// - TLS SNI, if enabled with [`ConnectionInfoConfig::sni_host_fallback`]
+    /// - Server hostname
+    ///
+    /// For HTTP/1 requests with an absolute-form target (eg. `GET http://example.com/ HTTP/1.1`),
+    /// the URI authority is used in place of the Host header, as required by RFC 7230 §5.4. The
+    /// same can be enabled for HTTP/2 with [`ConnectionInfoConfig::prefer_http2_authority`].
+    /// Otherwise the Host header is used as a whole, so with `Host: example.com:8080` and an
+    /// `:authority` of `example.com:443` the host is `example.com:8080`; ports are never combined
+    /// from the two.
+    ///
+    /// The order of the first two can be swapped with [`ConnectionInfoConfig::host_preference`].
+    /// A `Forwarded` host of `unknown` or an obfuscated identifier (eg. `_hidden`) is ignored.
+    ///
+    /// A single trailing dot of a fully qualified name is removed, so `example.com.` resolves to
+    /// `example.com`; see [`raw_host`](Self::raw_host) for the host as sent.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// Host as sent, before removing the trailing dot of a fully qualified name.
+    ///
+    /// Same as [`host`](Self::host) unless the host ended with a dot.
+    pub fn raw_host(&self) -> &str {
+        self.raw_host.as_deref().unwrap_or(&self.host)
+    }
+
+    /// Returns true if the [host](Self::host) is `name`, ignoring the port, ASCII case and a
+    /// trailing dot on either side.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default()
+    ///     .insert_header(("host", "Example.com.:8080"))
+    ///     .to_http_request();
+    /// assert!(req.connection_info().matches_host("example.com"));
+    /// ```
+    pub fn matches_host(&self, name: &str) -> bool {
+        let (hostname, _) = split_host_port(&self.host);
+        let hostname = hostname.strip_suffix('.').unwrap_or(hostname);
+        let name = name.strip_suffix('.').unwrap_or(name);
+
+        hostname.eq_ignore_ascii_case(name)
+    }
+
+    /// [Scheme](Self::scheme) and [host](Self::host) of the request, resolved together.
+    #[inline]
+    pub fn scheme_and_host(&self) -> (&str, &str) {
+        (&self.scheme, &self.host)
+    }
+
+    /// Authority of the request, eg. for the `:authority` pseudo-header of a proxied HTTP/2
+    /// request.
+    ///
+    /// This is the [host](Self::host), including its port unless it is the default port of the
+    /// [scheme](Self::scheme); so `example.com:443` becomes `example.com` for `https` requests
+    /// while `example.com:8443` is unchanged.
+    pub fn authority(&self) -> String {
+        match split_host_port(&self.host) {
+            (hostname, Some(port)) if port.parse().ok() == default_port(&self.scheme) => {
+                hostname.to_owned()
+            }
+            _ => self.host.clone(),
+        }
+    }
+
+    /// Hostname of the request normalized to its ASCII (punycode) form.
+    ///
+    /// Unicode labels are converted using IDNA processing so that, for example, `bücher.example`
+    /// and `xn--bcher-kva.example` yield the same value. Any port is preserved and IP literals are
+    /// returned unchanged. Returns `None` if the host is not a valid internationalized domain name.
+    #[cfg(feature = "idna")]
+    pub fn host_idna_ascii(&self) -> Option<String> {
+        let (hostname, port) = split_host_port(&self.host);
+
+        let hostname = if parse_node_ip(hostname).is_some() {
+            hostname.to_owned()
+        } else {
+            idna::domain_to_ascii(hostname).ok()?
+        };
+
+        Some(match port {
+            Some(port) => format!("{}:{}", hostname, port),
+            None => hostname,
+        })
+    }
+
+    /// Sets the host, including any port, eg. in canonical-host middleware.
+    ///
+    /// Only this value is changed; use [`replace_cached`](Self::replace_cached) to make it visible
+    /// to downstream extractors.
+    pub fn set_host(&mut self, host: impl Into<String>) {
+        self.host = host.into();
+        self.raw_host = None;
+        self.host_source = HostSource::Replaced;
+    }
+
+    /// Returns true if the [host](Self::host), ignoring any port, is an IP literal rather than a
+    /// DNS name.
+    ///
+    /// Both IPv4 addresses and bracketed IPv6 addresses (eg. `[::1]:8080`) are recognized.
+    pub fn host_is_ip(&self) -> bool {
+        let (hostname, _) = split_host_port(&self.host);
+        parse_node_ip(hostname).is_some()
+    }
+
+    /// Base URL of the request's origin, eg. `https://example.com:8443/`.
+    ///
+    /// Useful for joining relative links. Default ports are omitted.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default()
+    ///     .insert_header(("x-forwarded-proto", "https"))
+    ///     .insert_header(("host", "example.com"))
+    ///     .to_http_request();
+    ///
+    /// let base = req.connection_info().base_url().unwrap();
+    /// assert_eq!(base.join("docs/intro").unwrap().as_str(), "https://example.com/docs/intro");
+    /// ```
+    pub fn base_url(&self) -> Result<url::Url, url::ParseError> {
+        url::Url::parse(&format!("{}://{}/", self.scheme, self.host))
+    }
+
+    /// Returns true if the request's `Origin` header matches this connection's origin.
+    ///
+    /// The `Referer` header is consulted when no `Origin` header is present. Scheme, host and
+    /// port are compared after normalization, so `HTTPS://Example.com:443` matches
+    /// `https://example.com`. `Origin: null`, unparsable values, and requests with neither header
+    /// are never considered same-origin.
+    ///
+    /// Useful as part of CSRF mitigation for state-changing requests.
+    pub fn is_same_origin(&self, req: &RequestHead) -> bool {
+        let source = match req.headers.get(&header::ORIGIN) {
+            Some(origin) => origin,
+            None => match req.headers.get(&header::REFERER) {
+                Some(referer) => referer,
+                None => return false,
+            },
+        };
+
+        let source = match header_str(source).map(url::Url::parse) {
+            Some(Ok(url)) => url.origin(),
+            _ => return false,
+        };
+
+        match self.base_url() {
+            Ok(base) => source.is_tuple() && source == base.origin(),
+            Err(_) => false,
+        }
+    }
+
+    /// Remote address of the connection.
+    ///
+    /// Get remote_addr address from socket address, falling back to the
+    /// [synthetic peer address](ConnectionInfoConfig::synthetic_peer_addr) if one is configured.
+    pub fn remote_addr(&self) -> Option<&str> {
+        self.remote_addr.as_deref()
+    }
+
+    /// Socket address of the peer, with the same fallback as [`remote_addr`](Self::remote_addr).
+    ///
+    /// Avoids parsing the string form when the typed address is needed.
+    #[inline]
+    pub fn peer_socket_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Looks up the PTR hostname of the peer address through the configured
+    /// [resolver](ConnectionInfoConfig::peer_hostname_resolver).
+    ///
+    /// Returns `None` if no resolver is configured, the peer address is unknown, or the lookup
+    /// yields no name. Reverse lookups involve network round-trips and can be slow; avoid calling
+    /// this on hot paths or awaiting it before responding. The returned name is not verified
+    /// against forward DNS and must not be used for access control.
+    ///
+    /// Requires the `reverse-dns` feature.
+    #[cfg(feature = "reverse-dns")]
+    pub async fn resolve_peer_hostname(&self) -> Option<String> {
+        let ip = self.remote_addr.as_deref().and_then(parse_node_ip)?;
+        let resolver = self.resolver.clone()?;
+        resolver.0.resolve(ip).await
+    }
+
+    /// Registrable domain (eTLD+1) of the [host](Self::host), eg. `example.co.uk` for
+    /// `a.b.example.co.uk`, through the configured
+    /// [public suffix list](ConnectionInfoConfig::public_suffix_list).
+    ///
+    /// Useful for scoping the `Domain` attribute of cookies to the whole site. The port is ignored
+    /// and the domain is lowercased. Returns `None` if no list is configured, the host is an IP
+    /// address, or the host is itself a public suffix.
+    ///
+    /// The result is only as trustworthy as the host it is derived from, which comes from request
+    /// headers. Configure [trusted proxies](ConnectionInfoConfig::trusted_proxies) so clients
+    /// cannot pick the cookie domain through `Host` or forwarding headers, or check the result
+    /// against the domains the app serves.
+    ///
+    /// Requires the `registrable-domain` feature.
+    #[cfg(feature = "registrable-domain")]
+    pub fn registrable_domain(&self) -> Option<String> {
+        if self.host_is_ip() {
+            return None;
+        }
+
+        let (hostname, _) = split_host_port(&self.host);
+        let hostname = hostname.to_ascii_lowercase();
+        let suffix = self.suffix_list.as_ref()?.0.public_suffix(&hostname)?;
+
+        // one more label than the public suffix
+        let prefix = hostname.strip_suffix(suffix)?.strip_suffix('.')?;
+        let label_start = prefix.rfind('.').map_or(0, |idx| idx + 1);
+
+        if prefix[label_start..].is_empty() {
+            return None;
+        }
+
+        Some(hostname[label_start..].to_owned())
+    }
+
+    /// Real IP (remote address) of client that initiated request.
+    ///
+    /// The address is resolved from the first of these sources that provides one:
+    ///
+    /// - the [custom resolver](ConnectionInfoConfig::realip_resolver), which replaces all of the
+    ///   header sources below when configured
+    /// - the [JSON header](ConnectionInfoConfig::realip_json_header), the
+    ///   [edge metadata header](ConnectionInfoConfig::edge_metadata_header) and Envoy's
+    ///   [external address](ConnectionInfoConfig::envoy) header, in this order, when configured
+    /// - the `for` chain of the Forwarded header or, if that has no nodes, X-Forwarded-For; with
+    ///   [trusted proxies](ConnectionInfoConfig::trusted_proxies) configured, the chain is walked
+    ///   from the nearest hop to the first untrusted node, otherwise the hop selected by
+    ///   [`ConnectionInfoConfig::realip_strategy`], the first by default, is used
+    /// - remote_addr name of opened socket
+    ///
+    /// Forwarding headers are only consulted when they are honored, eg. not from an untrusted peer
+    /// when trusted proxies are configured.
+    ///
+    /// # Security
+    /// Do not use this function for security purposes, unless you can ensure the Forwarded and
+    /// X-Forwarded-For headers cannot be spoofed by the client. If you want the client's socket
+    /// address explicitly, use [`HttpRequest::peer_addr()`][peer_addr] instead.
+    ///
+    /// [peer_addr]: crate::web::HttpRequest::peer_addr()
+    #[inline]
+    pub fn realip_remote_addr(&self) -> Option<&str> {
+        self.realip_remote_addr
+            .as_deref()
+            .or_else(|| self.remote_addr.as_deref())
+    }
+
+    /// Where the [real IP](Self::realip_remote_addr) was resolved from.
+    ///
+    /// Returns `None` when no real IP is available.
+    pub fn realip_source(&self) -> Option<RealIpSource> {
+        match (&self.realip_remote_addr, &self.remote_addr) {
+            (Some(_), _) => Some(self.realip_source),
+            (None, Some(_)) => Some(RealIpSource::Peer),
+            (None, None) => None,
+        }
+    }
+
+    /// Real IP of the client, falling back to the peer address.
+    ///
+    /// Provided for compatibility with code written against older versions of actix-web, where
+    /// this method was the only way to get the client address. It is an alias for
+    /// [`realip_remote_addr`](Self::realip_remote_addr), which new code should prefer since its
+    /// name makes clear that the value may come from client-controlled headers.
+    #[inline]
+    pub fn remote(&self) -> Option<&str> {
+        self.realip_remote_addr()
+    }
+
+    /// `X-Forwarded-For` value to send to the next hop when proxying this request.
+    ///
+    /// Appends `peer`, usually the [peer address](Self::remote_addr) of this request, to the
+    /// entries of the received `X-Forwarded-For` headers. Returns just `peer` when there were none.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::net::IpAddr;
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default()
+    ///     .insert_header(("x-forwarded-for", "192.0.2.60"))
+    ///     .to_http_request();
+    ///
+    /// let peer = IpAddr::from([198, 51, 100, 17]);
+    /// assert_eq!(
+    ///     req.connection_info().next_x_forwarded_for(peer),
+    ///     "192.0.2.60, 198.51.100.17"
+    /// );
+    /// ```
+    pub fn next_x_forwarded_for(&self, peer: IpAddr) -> String {
+        match self.lazy.x_forwarded_for() {
+            Some(list) => format!("{}, {}", list, peer),
+            None => peer.to_string(),
+        }
+    }
+
+    /// Number of entries in the forwarded chain.
+    ///
+    /// Counts the `Forwarded` `for` nodes, or `X-Forwarded-For` entries if there are none. Useful
+    /// for spotting unexpectedly long chains, eg. caused by routing loops.
+    ///
+    /// Only the 64 hops nearest to this server are retained by the chain accessors, eg.
+    /// [`proxy_path`](Self::proxy_path) and
+    /// [`next_x_forwarded_for`](Self::next_x_forwarded_for); this count includes all hops, so
+    /// [`validate_hop_count`](Self::validate_hop_count) still rejects longer chains.
+    pub fn hop_count(&self) -> usize {
+        self.hop_count
+    }
+
+    /// Checks that the forwarded chain has at most `max` entries.
+    ///
+    /// Entries are counted as in [`hop_count`](Self::hop_count). Rejecting longer chains protects
+    /// ingresses with a known number of proxies against routing loops and injected entries; the
+    /// returned error responds with a 400 Bad Request.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default()
+    ///     .insert_header(("x-forwarded-for", "192.0.2.60, 198.51.100.17, 203.0.113.43"))
+    ///     .to_http_request();
+    /// let info = req.connection_info();
+    ///
+    /// assert!(info.validate_hop_count(3).is_ok());
+    /// assert_eq!(info.validate_hop_count(2).unwrap_err().hops(), 3);
+    /// ```
+    pub fn validate_hop_count(&self, max: usize) -> Result<(), TooManyHops> {
+        let hops = self.hop_count();
+
+        if hops > max {
+            Err(TooManyHops { hops, max })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// `(by, for)` node pairs of each `Forwarded` element, in order.
+    ///
+    /// Each pair records which proxy (`by`) received the connection from which client or upstream
+    /// proxy (`for`), tracing the request's path through named proxies. Nodes are unquoted but
+    /// otherwise returned as sent, including obfuscated identifiers. Elements containing neither
+    /// parameter are skipped.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default()
+    ///     .insert_header(("forwarded", "for=192.0.2.60;by=_edge, for=_edge;by=_lb"))
+    ///     .to_http_request();
+    /// let path = req.connection_info().proxy_path();
+    /// assert_eq!(path[0], (Some("_edge".to_owned()), Some("192.0.2.60".to_owned())));
+    /// ```
+    pub fn proxy_path(&self) -> Vec<(Option<String>, Option<String>)> {
+        self.lazy.proxy_path().to_vec()
+    }
+
+    /// `Forwarded` value to send upstream with the last element removed.
+    ///
+    /// When proxying, the last element usually describes the hop from the immediate peer to this
+    /// server, which is removed to avoid counting it twice once this server adds its own element.
+    /// Elements of all `Forwarded` headers are joined in order and otherwise passed on as
+    /// received. Returns `None` when fewer than two elements were received.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default()
+    ///     .insert_header(("forwarded", "for=192.0.2.60;proto=https, for=198.51.100.17"))
+    ///     .to_http_request();
+    /// assert_eq!(
+    ///     req.connection_info().forwarded_without_last_hop().as_deref(),
+    ///     Some("for=192.0.2.60;proto=https"),
+    /// );
+    /// ```
+    pub fn forwarded_without_last_hop(&self) -> Option<String> {
+        let (_, rest) = self.lazy.forwarded_elements().split_last()?;
+
+        if rest.is_empty() {
+            None
+        } else {
+            Some(rest.join(", "))
+        }
+    }
+
+    /// Returns true if two or more distinct public IP addresses appear in the forwarded chain.
+    ///
+    /// Purely heuristic: multiple public hops may indicate a carrier-grade NAT, a corporate proxy
+    /// or a privacy relay in front of the client. The `Forwarded` `for` nodes are checked, or
+    /// `X-Forwarded-For` entries if there are none; obfuscated nodes and the peer address are not
+    /// considered.
+    pub fn has_multiple_public_hops(&self) -> bool {
+        let mut public_ips = self
+            .forwarded_for
+            .iter()
+            .filter_map(|node| parse_node_ip(node))
+            .map(canonical_ip)
+            .filter(|ip| is_public_ip(*ip));
+
+        match public_ips.next() {
+            Some(first) => public_ips.any(|ip| ip != first),
+            None => false,
+        }
+    }
+
+    /// Configured [trusted proxy](ConnectionInfoConfig::trusted_proxies) network that the peer
+    /// address fell into during real IP resolution, eg. for audit logs.
+    ///
+    /// Single trusted addresses are reported as host networks (eg. `10.0.0.1/32`). Returns `None`
+    /// when no trusted proxies are configured, the peer is not trusted, or the real IP came from a
+    /// [custom resolver](ConnectionInfoConfig::realip_resolver), the
+    /// [JSON header](ConnectionInfoConfig::realip_json_header), the
+    /// [edge metadata header](ConnectionInfoConfig::edge_metadata_header) or Envoy's
+    /// [external address](ConnectionInfoConfig::envoy) header.
+    pub fn trusted_proxy_matched(&self) -> Option<&IpNet> {
+        self.trusted_proxy.as_ref()
+    }
+
+    /// First node of the forwarded chain that is an address of the requested family.
+    ///
+    /// Scans the `Forwarded` `for` nodes, or `X-Forwarded-For` entries if there are none, from the
+    /// client outwards and returns the first IPv6 address when `v6` is true, otherwise the first
+    /// IPv4 address. The node is returned as sent, including any port. Unlike
+    /// [`realip_remote_addr`](Self::realip_remote_addr), trusted proxy configuration is not
+    /// applied and the peer address is not considered.
+    pub fn realip_of_family(&self, v6: bool) -> Option<&str> {
+        self.forwarded_for
+            .iter()
+            .find(|node| match parse_node_ip(node) {
+                Some(ip) => ip.is_ipv6() == v6,
+                None => false,
+            })
+            .map(String::as_str)
+    }
+
+    /// Compares the scheme, host, forwarded real IP and peer IP of two connection infos.
+    ///
+    /// The peer port is ignored, as it changes from connection to connection, making this suitable
+    /// for golden tests; including when the [real IP](Self::realip_remote_addr) falls back to the
+    /// peer address. Other resolved details, such as the forwarded chain or
+    /// connection timing, are not compared.
+    pub fn semantically_eq(&self, other: &ConnectionInfo) -> bool {
+        let peer_ip = |info: &ConnectionInfo| {
+            info.remote_addr
+                .as_deref()
+                .map(|addr| parse_node_ip(addr).ok_or_else(|| addr.to_owned()))
+        };
+
+        self.scheme == other.scheme
+            && self.host == other.host
+            && self.realip_remote_addr == other.realip_remote_addr
+            && peer_ip(self) == peer_ip(other)
+    }
+
+    /// Port of the client's connection, resolved from the first available of, in this order:
+    ///
+    /// - Port of the client `for` node of the `Forwarded` header, eg. `for="192.0.2.60:4711"`
+    /// - X-Forwarded-Port
+    /// - Port of the [host](Self::host)
+    /// - Port of the peer socket address
+    ///
+    /// Obfuscated and malformed ports are ignored.
+    pub fn client_port(&self) -> Option<u16> {
+        self.forwarded_client_port
+            .or(self.forwarded_port)
+            .or_else(|| split_host_port(&self.host).1?.parse().ok())
+            .or_else(|| self.peer_addr.map(|addr| addr.port()))
+    }
+
+    /// Returns false if the port of the [host](Self::host) disagrees with the X-Forwarded-Port
+    /// header.
+    ///
+    /// A request for `example.com:80` that a proxy reports as received on port 443 may have been
+    /// tampered with. Returns true when the ports agree or when either one is absent.
+    pub fn port_consistent(&self) -> bool {
+        let host_port = split_host_port(&self.host)
+            .1
+            .and_then(|port| port.parse::<u16>().ok());
+
+        match (host_port, self.forwarded_port) {
+            (Some(host_port), Some(forwarded_port)) => host_port == forwarded_port,
+            _ => true,
+        }
+    }
+
+    /// Country code reported by the edge, eg. a CDN.
+    ///
+    /// Read from the header set with [`ConnectionInfoConfig::geo_header`]; always `None` if no
+    /// header is configured. The value is passed through as sent, typically an ISO 3166-1
+    /// alpha-2 code such as `DE`.
+    pub fn country_code(&self) -> Option<&str> {
+        self.lazy.country_code()
+    }
+
+    /// Client field (`%h`) for the Common and Combined Log Formats.
+    ///
+    /// The [real IP](Self::realip_remote_addr) without a port, or `-` when unavailable or not an IP
+    /// address. Configure [trusted proxies](ConnectionInfoConfig::trusted_proxies) to keep the
+    /// field from being spoofed with forwarding headers.
+    pub fn clf_client(&self) -> String {
+        match self.realip_remote_addr().and_then(parse_node_ip) {
+            Some(ip) => ip.to_string(),
+            None => "-".to_owned(),
+        }
+    }
+
+    /// Consumes the connection info, returning its scheme, host, real IP and peer address.
+    ///
+    /// The last two are the same values returned by
+    /// [`realip_remote_addr`](Self::realip_remote_addr) and [`remote_addr`](Self::remote_addr).
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default().to_http_request();
+    /// let (scheme, host, _realip, _peer) = req.connection_info().clone().into_parts();
+    ///
+    /// assert_eq!(scheme, "http");
+    /// assert_eq!(host, "localhost:8080");
+    /// ```
+    pub fn into_parts(self) -> (String, String, Option<String>, Option<String>) {
+        let remote_addr = self.remote_addr;
+        let realip = self.realip_remote_addr.or_else(|| remote_addr.clone());
+        (self.scheme, self.host, realip, remote_addr)
+    }
+
+    /// Borrowed view of the resolved fields, eg. for recording in structured logs.
+    ///
+    /// Does not allocate. `realip` is the same value returned by
+    /// [`realip_remote_addr`](Self::realip_remote_addr) and `peer` the same as
+    /// [`remote_addr`](Self::remote_addr).
+    pub fn fields(&self) -> ConnectionInfoFields<'_> {
+        ConnectionInfoFields {
+            scheme: self.scheme(),
+            host: self.host(),
+            realip: self.realip_remote_addr(),
+            peer: self.remote_addr(),
+        }
+    }
+
+    /// Resolved values as [OpenTelemetry semantic convention][semconv] attributes.
+    ///
+    /// Returns `url.scheme`, `server.address` and `server.port` from the scheme and host,
+    /// `client.address` from the [real IP](Self::realip_remote_addr) and `network.peer.address`
+    /// and `network.peer.port` from the peer address. `client.port` is only returned from the port
+    /// of the `Forwarded` `for` node the real IP was taken from, or from the peer port when the
+    /// real IP is the peer itself. Attributes whose
+    /// value is unavailable are omitted, as are client addresses that are not IP addresses.
+    ///
+    /// Addresses borrow from this info and ports are integers, as the conventions specify; this
+    /// crate does not depend on a particular `opentelemetry` version, so convert each pair
+    /// with `KeyValue::new`.
+    ///
+    /// Requires the `otel-attributes` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::{dev::OtelValue, test::TestRequest};
+    ///
+    /// let req = TestRequest::default().to_http_request();
+    /// let info = req.connection_info();
+    /// let attrs = info.otel_attributes();
+    ///
+    /// assert!(attrs.contains(&("url.scheme", OtelValue::Str("http"))));
+    /// assert!(attrs.contains(&("server.address", OtelValue::Str("localhost"))));
+    /// ```
+    ///
+    /// [semconv]: https://opentelemetry.io/docs/specs/semconv/http/http-spans/
+    #[cfg(feature = "otel-attributes")]
+    pub fn otel_attributes(&self) -> Vec<(&'static str, OtelValue<'_>)> {
+        let mut attrs = vec![("url.scheme", OtelValue::Str(&self.scheme))];
+
+        let (hostname, port) = split_host_port(&self.host);
+        let hostname = hostname.trim_start_matches('[').trim_end_matches(']');
+        attrs.push(("server.address", OtelValue::Str(hostname)));
+        if let Some(port) = port.and_then(|port| port.parse::<u16>().ok()) {
+            attrs.push(("server.port", OtelValue::Int(port.into())));
+        }
+
+        if let Some(ip) = self.realip_remote_addr().and_then(node_ip_str) {
+            attrs.push(("client.address", OtelValue::Str(ip)));
+
+            // unlike `client_port`, never falls back to a port of a proxy or of this server
+            let port = match self.realip_source() {
+                Some(RealIpSource::Forwarded) => self.forwarded_client_port,
+                Some(RealIpSource::Peer) => self.peer_addr.map(|addr| addr.port()),
+                _ => None,
+            };
+            if let Some(port) = port {
+                attrs.push(("client.port", OtelValue::Int(port.into())));
+            }
+        }
+
+        if let Some(peer) = self.peer_addr {
+            if let Some(ip) = self.remote_addr.as_deref().and_then(node_ip_str) {
+                attrs.push(("network.peer.address", OtelValue::Str(ip)));
+            }
+            attrs.push(("network.peer.port", OtelValue::Int(peer.port().into())));
+        }
+
+        attrs
+    }
+
+    /// Intermediate proxies listed in the `Via` header, in the order the request passed through
+    /// them.
+    ///
+    /// Comments following a hop are discarded. Elements without both a protocol and a
+    /// received-by value are skipped.
+    pub fn via_chain(&self) -> &[ViaHop] {
+        self.lazy.via_chain()
+    }
+
+    /// Value of a `Sec-CH-*` client hint header, eg. `Sec-CH-UA-Platform`, as sent.
+    ///
+    /// `name` is the full header name and is matched case-insensitively. Only the first value of
+    /// a repeated header is returned. Client hints are self-reported by the user agent and should
+    /// only be used as a heuristic.
+    pub fn client_hint(&self, name: &str) -> Option<&str> {
+        self.lazy.client_hint(name)
+    }
+
+    /// Which forwarding headers the request carried, whether or not they were used.
+    ///
+    /// Useful for spotting proxies that send inconsistent sets of headers.
+    pub fn present_forwarding_headers(&self) -> ForwardingHeadersPresent {
+        self.forwarding_headers
+    }
+
+    /// Returns a copy with client and proxy addresses anonymized, eg. for logging.
+    ///
+    /// Addresses are masked to their /24 network for IPv4 and /48 network for IPv6, and ports are
+    /// dropped; so `192.0.2.60:8080` becomes `192.0.2.0` and `2001:db8:85a3::8a2e:370:7334`
+    /// becomes `2001:db8:85a3::`. This covers the [real IP](Self::realip_remote_addr), the
+    /// [peer address](Self::remote_addr), the `for` and `by` nodes of the forwarded chains and
+    /// `Via` hops, so neither the getters nor the `Debug` output of the copy contain a full
+    /// address. A real IP or peer address that is not an IP address is removed, as is the
+    /// [peer socket address](Self::peer_socket_addr); other nodes that are not addresses, eg.
+    /// obfuscated identifiers, are kept.
+    pub fn redacted(&self) -> ConnectionInfo {
+        let mut info = self.clone();
+        info.anonymize_addresses();
+        info
+    }
+
+    /// Checks the resolved host against the TLS server name (SNI) the client requested.
+    ///
+    /// The port and any trailing dot are ignored and names are compared case-insensitively.
+    /// Returns `None` when no SNI was recorded in [`TlsInfo`] connection data. A mismatch can
+    /// indicate domain fronting.
+    pub fn host_matches_sni(&self, req: &RequestHead) -> Option<bool> {
+        let conn = ConnectionConstants::of(req);
+        let sni = conn.sni.as_deref()?;
+
+        let (host, _) = split_host_port(&self.host);
+        let host = host.trim_end_matches('.');
+        let sni = sni.trim_end_matches('.');
+
+        Some(host.eq_ignore_ascii_case(sni))
+    }
+
+    /// Returns false if the resolved scheme disagrees with whether this connection uses TLS.
+    ///
+    /// The connection counts as TLS when [`TlsInfo`] connection data is present or the server
+    /// accepted it over TLS. A secure scheme (`https` or `wss`) on a plaintext connection, or an
+    /// insecure scheme on a TLS connection, is inconsistent and logged as a warning. This may
+    /// indicate a downgrade attack or misconfigured proxy.
+    ///
+    /// Only meaningful when this server terminates TLS itself; behind a TLS-terminating proxy
+    /// `https` requests arrive over plaintext connections.
+    pub fn scheme_tls_consistent(&self, req: &RequestHead) -> bool {
+        let is_tls = self.is_tls(req);
+
+        if is_tls != self.is_secure() {
+            log::warn!(
+                "Resolved scheme {:?} (from {}) does not match {} connection",
+                self.scheme,
+                self.scheme_source.describe(),
+                if is_tls { "TLS" } else { "plaintext" },
+            );
+
+            return false;
+        }
+
+        true
+    }
+
+    /// Returns true if the request is known to be HTTPS without trusting forwarding headers.
+    ///
+    /// TLS, either accepted by this server or recorded as [`TlsInfo`] connection data, is
+    /// considered, as is an `https` request URI when the peer is a configured
+    /// [trusted proxy](ConnectionInfoConfig::trusted_proxies); clients can send any URI scheme over
+    /// plaintext. `Forwarded`, `X-Forwarded-Proto` and similar headers, which clients behind a
+    /// naive proxy can set freely, are ignored. Unlike [`scheme`](Self::scheme), this is therefore
+    /// false for requests served over plaintext by a TLS-terminating proxy.
+    pub fn scheme_is_https_strict(&self, req: &RequestHead) -> bool {
+        self.is_tls(req)
+            || (self.trusted_forwarding && req.uri.scheme() == Some(&Scheme::HTTPS))
+    }
+
+    /// Human-readable explanation of where each resolved value came from.
+    ///
+    /// Produces one line per field, intended for diagnostic endpoints and debugging proxy setups:
+    ///
+    /// ```text
+    /// scheme=https (from X-Forwarded-Proto)
+    /// host=example.com (from Host header)
+    /// realip=192.0.2.60 (from Forwarded)
+    /// ```
+    ///
+    /// The output format is not stable and should not be parsed.
+    pub fn explain(&self) -> String {
+        let realip = match (self.realip_remote_addr(), self.realip_source()) {
+            (Some(realip), Some(source)) => format!("{} (from {})", realip, source.describe()),
+            _ => "- (unavailable)".to_owned(),
+        };
+
+        format!(
+            "scheme={} (from {})\nhost={} (from {})\nrealip={}",
+            self.scheme,
+            self.scheme_source.describe(),
+            self.host,
+            self.host_source.describe(),
+            realip,
+        )
+    }
+
+    /// Stable hash of the request's origin, suitable for keying caches.
+    ///
+    /// Scheme and host are compared case-insensitively and the host's port is ignored when it is
+    /// the scheme's default, so `HTTPS://Example.com:443` and `https://example.com` hash equally.
+    /// The hash function is fixed and yields the same value across processes and restarts.
+    pub fn origin_hash(&self) -> u64 {
+        let (hostname, port) = split_host_port(&self.host);
+
+        let host = match port {
+            Some(port) if port.parse().ok() == default_port(&self.scheme) => hostname,
+            _ => &self.host,
+        };
+
+        let origin = self
+            .scheme
+            .bytes()
+            .chain(b"://".iter().copied())
+            .chain(host.bytes())
+            .map(|b| b.to_ascii_lowercase());
+
+        fnv1a(origin)
+    }
+
+    /// Time at which the underlying connection was established.
+    ///
+    /// Only available when a [`ConnectionEstablishedAt`] value was stored in connection data, eg.
+    /// by a [`HttpServer::on_connect`](crate::HttpServer::on_connect) callback.
+    #[inline]
+    pub fn connection_established_at(&self) -> Option<Instant> {
+        self.established_at
+    }
+
+    /// How HTTP/2 was negotiated for the underlying connection.
+    ///
+    /// Only available for HTTP/2 requests when an [`Http2Negotiation`] value was stored in
+    /// connection data. Always `None` for other HTTP versions.
+    #[inline]
+    pub fn http2_negotiation(&self) -> Option<Http2Negotiation> {
+        self.http2_negotiation
+    }
+
+    /// Transport of the underlying connection.
+    ///
+    /// Read from [`Transport`] connection data. When none was stored, connections with a socket
+    /// peer address are assumed to be [`Tcp`](Transport::Tcp), those with [`QuicPeerAddr`]
+    /// connection data [`Quic`](Transport::Quic) and others [`Unknown`](Transport::Unknown).
+    #[inline]
+    pub fn transport(&self) -> Transport {
+        self.transport
+    }
+}
+
+impl FromRequest for ConnectionInfo {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ConnectionInfoConfig;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(req.connection_info().clone())
+    }
+}
+
+/// Extractor for [`ConnectionInfo`] that rejects ambiguous requests.
+///
+/// Unlike the lenient `ConnectionInfo` extractor, this fails with a 400 Bad Request when the
+/// request carries signals that are commonly associated with request smuggling or broken proxies:
+///
+/// - more than one `Host` header ([RFC 7230 §5.4][rfc7230-54])
+/// - a `Forwarded` header without any elements
+/// - a `Forwarded` `for` node that is an IPv6 address without brackets, eg. `for=2001:db8::1`,
+///   which the lenient extractor accepts and normalizes to `[2001:db8::1]`
+///
+/// [rfc7230-54]: https://datatracker.ietf.org/doc/html/rfc7230#section-5.4
+///
+/// # Examples
+/// ```
+/// # use actix_web::Responder;
+/// use actix_web::dev::StrictConnectionInfo;
+///
+/// async fn handler(conn: StrictConnectionInfo) -> impl Responder {
+///     format!("Hello, {}!", conn.host())
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StrictConnectionInfo(ConnectionInfo);
+
+impl StrictConnectionInfo {
+    /// Unwrap into inner `ConnectionInfo` value.
+    pub fn into_inner(self) -> ConnectionInfo {
+        self.0
+    }
+
+    fn validate(req: &RequestHead) -> Result<(), ConnectionInfoError> {
+        if req.headers.get_all(&header::HOST).count() > 1 {
+            return Err(ConnectionInfoError::MultipleHostHeaders);
+        }
+
+        if forwarded_is_empty(req) {
+            return Err(ConnectionInfoError::EmptyForwardedHeader);
+        }
+
+        if forwarded_header_elements(req)
+            .any(|(_, elem)| elem.for_node().map_or(false, is_unbracketed_ipv6))
+        {
+            return Err(ConnectionInfoError::UnbracketedIpv6);
+        }
+
+        Ok(())
+    }
+}
+
+impl Deref for StrictConnectionInfo {
+    type Target = ConnectionInfo;
+
+    fn deref(&self) -> &ConnectionInfo {
+        &self.0
+    }
+}
+
+impl FromRequest for StrictConnectionInfo {
+    type Error = ConnectionInfoError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ConnectionInfoConfig;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match Self::validate(req.head()) {
+            Ok(()) => ok(StrictConnectionInfo(req.connection_info().clone())),
+            Err(e) => {
+                log::debug!("Rejecting ambiguous request: {}", e);
+                err(e)
+            }
+        }
+    }
+}
+
+/// Extractor for [`ConnectionInfo`] that hands resolution problems to the handler.
+///
+/// Performs the same checks as [`StrictConnectionInfo`] and additionally reports
+/// [`ConnectionInfoError::MissingHost`] when the request names no host, in which case the lenient
+/// extractor falls back to the server hostname. Never fails the request; the handler decides how
+/// to respond.
+///
+/// # Examples
+/// ```
+/// use actix_web::{dev::TryConnectionInfo, HttpResponse, Responder};
+///
+/// async fn handler(conn: TryConnectionInfo) -> impl Responder {
+///     match conn.0 {
+///         Ok(conn) => HttpResponse::Ok().body(format!("Hello, {}!", conn.host())),
+///         Err(err) => HttpResponse::Ok().body(format!("Hello, stranger! ({})", err)),
+///     }
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug)]
+pub struct TryConnectionInfo(pub Result<ConnectionInfo, ConnectionInfoError>);
+
+impl TryConnectionInfo {
+    /// Unwrap into inner result.
+    pub fn into_inner(self) -> Result<ConnectionInfo, ConnectionInfoError> {
+        self.0
+    }
+}
+
+impl FromRequest for TryConnectionInfo {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ConnectionInfoConfig;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let res = StrictConnectionInfo::validate(req.head()).and_then(|()| {
+            let info = req.connection_info();
+
+            if info.host_source == HostSource::AppConfig {
+                Err(ConnectionInfoError::MissingHost)
+            } else {
+                Ok(info.clone())
+            }
+        });
+
+        if let Err(ref e) = res {
+            log::debug!("Connection info resolution problem: {}", e);
+        }
+
+        ok(TryConnectionInfo(res))
+    }
+}
+
+/// Errors that can occur when extracting connection information, eg. a
+/// [`StrictConnectionInfo`] or [`AbsoluteUri`].
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ConnectionInfoError {
+    /// Request contains more than one `Host` header.
+    #[display(fmt = "Request contains multiple Host headers")]
+    MultipleHostHeaders,
+
+    /// Request contains a `Forwarded` header without any elements.
+    #[display(fmt = "Request contains an empty Forwarded header")]
+    EmptyForwardedHeader,
+
+    /// Request contains a `Forwarded` `for` node that is an IPv6 address without brackets.
+    #[display(fmt = "Request contains an unbracketed IPv6 address in the Forwarded header")]
+    UnbracketedIpv6,
+
+    /// Resolved scheme and host do not form a valid absolute URI.
+    #[display(fmt = "Request URI could not be made absolute")]
+    InvalidAbsoluteUri,
+
+    /// Request does not name a host in any header or its URI.
+    ///
+    /// Only reported by [`TryConnectionInfo`].
+    #[display(fmt = "Request does not specify a host")]
+    MissingHost,
+}
+
+impl ResponseError for ConnectionInfoError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Source from which [`ConnectionInfo::scheme`] was resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SchemeSource {
+    /// `proto` parameter of the `Forwarded` header.
+    Forwarded,
+
+    /// `X-Forwarded-Proto` header.
+    XForwardedProto,
+
+    /// `X-Forwarded-Scheme` header, used by some proxies in place of `X-Forwarded-Proto`.
+    XForwardedScheme,
+
+    /// `X-Forwarded-SSL: on` header, sent by some legacy proxies instead of a proto header.
+    XForwardedSsl,
+
+    /// The [edge metadata header](ConnectionInfoConfig::edge_metadata_header).
+    ///
+    /// Requires the `edge-metadata` feature.
+    #[cfg(feature = "edge-metadata")]
+    EdgeMetadata,
+
+    /// Scheme of the request URI, present for absolute-form and HTTP/2 requests.
+    Uri,
+
+    /// The connection was accepted over TLS by this server or carries [`TlsInfo`] connection data.
+    Tls,
+
+    /// No scheme information was available so the
+    /// [default scheme](ConnectionInfoConfig::default_scheme) was assumed.
+    Default,
+
+    /// Set with [`ConnectionInfo::set_scheme`].
+    Replaced,
+}
+
+impl SchemeSource {
+    /// Returns true for forwarding headers, which are only honored from trusted peers.
+    fn is_forwarding(self) -> bool {
+        match self {
+            SchemeSource::Forwarded
+            | SchemeSource::XForwardedProto
+            | SchemeSource::XForwardedScheme
+            | SchemeSource::XForwardedSsl => true,
+            #[cfg(feature = "edge-metadata")]
+            SchemeSource::EdgeMetadata => true,
+            SchemeSource::Uri
+            | SchemeSource::Tls
+            | SchemeSource::Default
+            | SchemeSource::Replaced => false,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            SchemeSource::Forwarded => "Forwarded",
+            SchemeSource::XForwardedProto => "X-Forwarded-Proto",
+            SchemeSource::XForwardedScheme => "X-Forwarded-Scheme",
+            SchemeSource::XForwardedSsl => "X-Forwarded-SSL",
+            #[cfg(feature = "edge-metadata")]
+            SchemeSource::EdgeMetadata => "edge metadata",
+            SchemeSource::Uri => "request URI",
+            SchemeSource::Tls => "TLS connection",
+            SchemeSource::Replaced => "replaced",
+            SchemeSource::Default => "default",
+        }
+    }
+}
+
+impl Default for SchemeSource {
+    fn default() -> Self {
+        SchemeSource::Default
+    }
+}
+
+/// Source from which [`ConnectionInfo::host`] was resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HostSource {
+    #[cfg(feature = "edge-metadata")]
+    EdgeMetadata,
+    Forwarded,
+    XForwardedHost,
+    Host,
+    Uri,
+    Sni,
+    AppConfig,
+    Replaced,
+}
+
+impl HostSource {
+    fn describe(self) -> &'static str {
+        match self {
+            #[cfg(feature = "edge-metadata")]
+            HostSource::EdgeMetadata => "edge metadata",
+            HostSource::Forwarded => "Forwarded",
+            HostSource::XForwardedHost => "X-Forwarded-Host",
+            HostSource::Host => "Host header",
+            HostSource::Uri => "request URI",
+            HostSource::Sni => "TLS SNI",
+            HostSource::AppConfig => "app config",
+            HostSource::Replaced => "replaced",
+        }
+    }
+}
+
+impl Default for HostSource {
+    fn default() -> Self {
+        HostSource::AppConfig
+    }
+}
+
+/// Source from which [`ConnectionInfo::realip_remote_addr`] was resolved.
+///
+/// See [`ConnectionInfo::realip_source`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RealIpSource {
+    /// `for` parameter of the `Forwarded` header.
+    Forwarded,
+
+    /// `X-Forwarded-For` header.
+    XForwardedFor,
+
+    /// The [custom resolver](ConnectionInfoConfig::realip_resolver).
+    Resolver,
+
+    /// The [JSON header](ConnectionInfoConfig::realip_json_header).
+    ///
+    /// Requires the `json-realip` feature.
+    #[cfg(feature = "json-realip")]
+    JsonHeader,
+
+    /// The [edge metadata header](ConnectionInfoConfig::edge_metadata_header).
+    ///
+    /// Requires the `edge-metadata` feature.
+    #[cfg(feature = "edge-metadata")]
+    EdgeMetadata,
+
+    /// Envoy's [external address](ConnectionInfoConfig::envoy) header.
+    Envoy,
+
+    /// The peer address, because no forwarded address was available or, with
+    /// [trusted proxies](ConnectionInfoConfig::trusted_proxies) configured, the peer is not
+    /// trusted.
+    Peer,
+}
+
+impl RealIpSource {
+    fn describe(self) -> &'static str {
+        match self {
+            RealIpSource::Forwarded => "Forwarded",
+            RealIpSource::XForwardedFor => "X-Forwarded-For",
+            RealIpSource::Resolver => "custom resolver",
+            RealIpSource::Envoy => "Envoy external address",
+            #[cfg(feature = "json-realip")]
+            RealIpSource::JsonHeader => "JSON header",
+            #[cfg(feature = "edge-metadata")]
+            RealIpSource::EdgeMetadata => "edge metadata",
+            RealIpSource::Peer => "peer address",
+        }
+    }
+}
+
+impl Default for RealIpSource {
+    fn default() -> Self {
+        RealIpSource::Forwarded
+    }
+}
+
+/// Borrowed view of [`ConnectionInfo`] fields.
+///
+/// See [`ConnectionInfo::fields`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct ConnectionInfoFields<'a> {
+    /// See [`ConnectionInfo::scheme`].
+    pub scheme: &'a str,
+
+    /// See [`ConnectionInfo::host`].
+    pub host: &'a str,
+
+    /// See [`ConnectionInfo::realip_remote_addr`].
+    pub realip: Option<&'a str>,
+
+    /// See [`ConnectionInfo::remote_addr`].
+    pub peer: Option<&'a str>,
+}
+
+/// Value of an attribute returned by [`ConnectionInfo::otel_attributes`].
+///
+/// Requires the `otel-attributes` feature.
+#[cfg(feature = "otel-attributes")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OtelValue<'a> {
+    /// String attribute, eg. `url.scheme`.
+    Str(&'a str),
+
+    /// Integer attribute, eg. `server.port`.
+    Int(i64),
+}
+
+/// An intermediate proxy from the [`Via`][rfc7230-57] header.
+///
+/// See [`ConnectionInfo::via_chain`].
+///
+/// [rfc7230-57]: https://datatracker.ietf.org/doc/html/rfc7230#section-5.7.1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViaHop {
+    /// Protocol the proxy received the request with, eg. `1.1` or `HTTP/2`.
+    pub protocol: String,
+
+    /// Host (optionally with port) or pseudonym of the proxy.
+    pub received_by: String,
+}
+
+/// Forwarding headers present on a request.
+///
+/// See [`ConnectionInfo::present_forwarding_headers`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ForwardingHeadersPresent {
+    /// `Forwarded` header.
+    pub forwarded: bool,
+
+    /// `X-Forwarded-For` header.
+    pub x_forwarded_for: bool,
+
+    /// `X-Forwarded-Host` header.
+    pub x_forwarded_host: bool,
+
+    /// `X-Forwarded-Proto` header.
+    pub x_forwarded_proto: bool,
+
+    /// `X-Real-IP` header.
+    pub x_real_ip: bool,
+}
+
+impl ForwardingHeadersPresent {
+    /// Returns true if none of the headers are present.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// [`ConnectionInfo`] resolution configuration.
+///
+/// Register in app data to change how connection information is resolved for requests handled by
+/// that app, scope or resource. Also applies to the [`PeerAddr`] extractor.
+///
+/// # Examples
+/// ```
+/// use actix_web::{dev::ConnectionInfoConfig, App};
+///
+/// // requests arrive over a Unix domain socket; report a fixed peer address instead of none
+/// let info_cfg = ConnectionInfoConfig::default()
+///     .synthetic_peer_addr("127.0.0.1:0".parse().unwrap());
+///
+/// let app = App::new().app_data(info_cfg);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectionInfoConfig {
+    synthetic_peer_addr: Option<SocketAddr>,
+    realip_strategy: RealIpStrategy,
+    trusted_proxies: Option<TrustedProxies>,
+    internal_node_names: Cow<'static, [String]>,
+    host_preference: HostPreference,
+    prefer_http2_authority: bool,
+    sni_host_fallback: bool,
+    honor_forwarded_header: bool,
+    require_tls_for_forwarding: bool,
+    trust_loopback: bool,
+    anonymize_ip: bool,
+    default_scheme: Cow<'static, str>,
+    geo_header: Option<HeaderName>,
+    realip_resolver: Option<SharedRealIpResolver>,
+    info_resolver: Option<SharedInfoResolver>,
+    envoy_external_address: Option<HeaderName>,
+    #[cfg(feature = "json-realip")]
+    realip_json_header: Option<(HeaderName, Cow<'static, str>)>,
+    #[cfg(feature = "edge-metadata")]
+    edge_metadata_header: Option<HeaderName>,
+    #[cfg(feature = "reverse-dns")]
+    resolver: Option<SharedResolver>,
+    #[cfg(feature = "registrable-domain")]
+    suffix_list: Option<SharedSuffixList>,
+}
+
+impl ConnectionInfoConfig {
+    /// Config for apps behind an [AWS Application Load Balancer][alb].
+    ///
+    /// The ALB appends the address of the connecting client to `X-Forwarded-For`, so counting
+    /// the ALB's own peer address as the last hop, the client is the second-to-last entry; that is,
+    /// the last `X-Forwarded-For` entry. Entries to its left were sent by the client and are not
+    /// trusted. The ALB does not set the `Forwarded` header but passes it through as sent, so it
+    /// is ignored. The ALB also sets `X-Forwarded-Proto` which is used for the scheme;
+    /// `X-Amzn-Trace-Id` does not affect resolution.
+    ///
+    /// Combine with [`trusted_proxies`](Self::trusted_proxies) listing the load balancer's subnets
+    /// to ignore forwarding headers of requests that bypass it.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::{dev::ConnectionInfoConfig, test::TestRequest};
+    ///
+    /// let req = TestRequest::default()
+    ///     .peer_addr("10.0.1.23:41320".parse().unwrap())
+    ///     .insert_header(("x-forwarded-for", "198.51.100.1, 203.0.113.7"))
+    ///     .app_data(ConnectionInfoConfig::aws_alb())
+    ///     .to_http_request();
+    ///
+    /// assert_eq!(req.connection_info().realip_remote_addr(), Some("203.0.113.7"));
+    /// ```
+    ///
+    /// [alb]: https://docs.aws.amazon.com/elasticloadbalancing/latest/application/x-forwarded-headers.html
+    pub fn aws_alb() -> Self {
+        Self::default()
+            .realip_strategy(RealIpStrategy::LastHop)
+            .forwarded_header(false)
+    }
+
+    /// Set a peer address to use when the transport does not provide one.
+    ///
+    /// Intended for non-TCP transports, such as Unix domain sockets, and test harnesses where
+    /// [`HttpRequest::peer_addr`] is always `None`. Real peer addresses always take precedence.
+    ///
+    /// The synthetic address is only reported, never checked against
+    /// [trusted proxies](Self::trusted_proxies), so a loopback address does not make every
+    /// request trusted through [`trust_loopback`](Self::trust_loopback). Forwarding headers of
+    /// requests without a real peer address are ignored when trusted proxies are configured.
+    pub fn synthetic_peer_addr(mut self, addr: SocketAddr) -> Self {
+        self.synthetic_peer_addr = Some(addr);
+        self
+    }
+
+    /// Set which entry of the forwarded chain is used as the real IP.
+    ///
+    /// Defaults to [`RealIpStrategy::FirstHop`]. Has no effect when
+    /// [trusted proxies](Self::trusted_proxies) are configured.
+    pub fn realip_strategy(mut self, strategy: RealIpStrategy) -> Self {
+        self.realip_strategy = strategy;
+        self
+    }
+
+    /// Set the resolver used by [`ConnectionInfo::resolve_peer_hostname`].
+    ///
+    /// Requires the `reverse-dns` feature.
+    #[cfg(feature = "reverse-dns")]
+    pub fn peer_hostname_resolver<R>(mut self, resolver: R) -> Self
+    where
+        R: PeerHostnameResolver + 'static,
+    {
+        self.resolver = Some(SharedResolver(Arc::new(resolver)));
+        self
+    }
+
+    /// Set the public suffix list used by [`ConnectionInfo::registrable_domain`].
+    ///
+    /// Requires the `registrable-domain` feature.
+    #[cfg(feature = "registrable-domain")]
+    pub fn public_suffix_list<L>(mut self, list: L) -> Self
+    where
+        L: PublicSuffixList + 'static,
+    {
+        self.suffix_list = Some(SharedSuffixList(Arc::new(list)));
+        self
+    }
+
+    /// Replace connection info resolution with a custom resolver.
+    ///
+    /// The resolver runs once per request, in place of all built-in header and peer address
+    /// handling; the other settings of this config only apply if it delegates to a
+    /// [`DefaultResolver`] constructed with them. The result is cached like the built-in one and
+    /// also determines [`ResolvedClientIp`].
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::dev::{
+    ///     AppConfig, ConnectionInfo, ConnectionInfoConfig, ConnectionInfoResolver,
+    ///     DefaultResolver, RequestHead,
+    /// };
+    ///
+    /// /// Reports a canonical host for every request.
+    /// struct CanonicalHost(DefaultResolver);
+    ///
+    /// impl ConnectionInfoResolver for CanonicalHost {
+    ///     fn resolve(&self, head: &RequestHead, cfg: &AppConfig) -> ConnectionInfo {
+    ///         let mut info = self.0.resolve(head, cfg);
+    ///         info.set_host("www.rust-lang.org");
+    ///         info
+    ///     }
+    /// }
+    ///
+    /// let info_cfg = ConnectionInfoConfig::default()
+    ///     .connection_info_resolver(CanonicalHost(DefaultResolver::default()));
+    /// ```
+    pub fn connection_info_resolver<R>(mut self, resolver: R) -> Self
+    where
+        R: ConnectionInfoResolver + 'static,
+    {
+        self.info_resolver = Some(SharedInfoResolver(Arc::new(resolver)));
+        self
+    }
+
+    /// Set which of `Forwarded` and `X-Forwarded-Host` is preferred when resolving the host.
+    ///
+    /// Defaults to [`HostPreference::Forwarded`], per RFC 7239. Resolution of other fields is not
+    /// affected.
+    pub fn host_preference(mut self, preference: HostPreference) -> Self {
+        self.host_preference = preference;
+        self
+    }
+
+    /// Set whether the `:authority` pseudo-header of HTTP/2 and later requests is preferred over
+    /// the `Host` header when resolving the host.
+    ///
+    /// Defaults to false, where `Host` wins, matching HTTP/1 origin-form requests. Forwarding
+    /// headers still take precedence over both.
+    pub fn prefer_http2_authority(mut self, prefer: bool) -> Self {
+        self.prefer_http2_authority = prefer;
+        self
+    }
+
+    /// Set whether the TLS server name (SNI) is used as the host when the request has no host.
+    ///
+    /// Only applies when no forwarding header, `Host` header or request URI authority provided a
+    /// host, which non-compliant HTTP/2 clients can cause by omitting `:authority`. The SNI is read
+    /// from [`TlsInfo`] connection data. Defaults to false, where the server hostname is used.
+    pub fn sni_host_fallback(mut self, enabled: bool) -> Self {
+        self.sni_host_fallback = enabled;
+        self
+    }
+
+    /// Set whether the `Forwarded` header is honored.
+    ///
+    /// Defaults to true. Disable behind proxies that only set the `X-Forwarded-*` headers and pass
+    /// a client-sent `Forwarded` header through unchanged, which would otherwise take precedence.
+    pub fn forwarded_header(mut self, honor: bool) -> Self {
+        self.honor_forwarded_header = honor;
+        self
+    }
+
+    /// Set whether forwarding headers are only honored on TLS connections.
+    ///
+    /// When true, `Forwarded` and all `X-Forwarded-*` headers are ignored unless the connection
+    /// was accepted over TLS by this server or carries [`TlsInfo`] connection data, so an attacker
+    /// with plaintext access to an internal network cannot inject them. Defaults to false.
+    pub fn require_tls_for_forwarding(mut self, require: bool) -> Self {
+        self.require_tls_for_forwarding = require;
+        self
+    }
+
+    /// Set the scheme assumed when no header, URI or TLS information indicates one.
+    ///
+    /// Defaults to `http`. Useful for services that are only reachable through a TLS mesh.
+    pub fn default_scheme(mut self, scheme: &str) -> Self {
+        self.default_scheme = Cow::Owned(scheme.to_owned());
+        self
+    }
+
+    /// Set the header that supplies [`ConnectionInfo::country_code`], eg. `CF-IPCountry`.
+    ///
+    /// Not set by default. Only configure a header that your edge always sets or strips, since
+    /// clients can send it themselves.
+    pub fn geo_header(mut self, name: HeaderName) -> Self {
+        self.geo_header = Some(name);
+        self
+    }
+
+    /// Resolve the real IP with a custom function.
+    ///
+    /// Takes precedence over all built-in real IP resolution, including
+    /// [trusted proxies](Self::trusted_proxies) and the
+    /// [real IP strategy](Self::realip_strategy). Returning `None` makes
+    /// [`ConnectionInfo::realip_remote_addr`] fall back to the peer address.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::dev::ConnectionInfoConfig;
+    ///
+    /// // only trust X-Forwarded-For when the edge proves itself with a shared secret
+    /// let info_cfg = ConnectionInfoConfig::default().realip_resolver(Box::new(|req| {
+    ///     let secret = req.headers.get("x-edge-secret")?;
+    ///
+    ///     if secret != "s3cr3t" {
+    ///         return None;
+    ///     }
+    ///
+    ///     req.headers.get("x-forwarded-for")?.to_str().ok()?.parse().ok()
+    /// }));
+    /// ```
+    pub fn realip_resolver(
+        mut self,
+        resolver: Box<dyn Fn(&RequestHead) -> Option<IpAddr> + Send + Sync>,
+    ) -> Self {
+        self.realip_resolver = Some(SharedRealIpResolver(Arc::from(resolver)));
+        self
+    }
+
+    /// Resolve the real IP from the `X-Envoy-External-Address` header set by [Envoy][envoy].
+    ///
+    /// For service meshes where an Envoy edge proxy sanitizes forwarding headers. Envoy only sets
+    /// the header for requests from outside the mesh; requests marked with
+    /// `X-Envoy-Internal: true`, and requests without the header, resolve the real IP as usual.
+    ///
+    /// Takes precedence over the forwarded chain, but not over a
+    /// [custom resolver](Self::realip_resolver). When [trusted proxies](Self::trusted_proxies)
+    /// are configured, the header is only honored if the peer is trusted. Disabled by default.
+    ///
+    /// [envoy]: https://www.envoyproxy.io/docs/envoy/latest/configuration/http/http_conn_man/headers#x-envoy-external-address
+    pub fn envoy(mut self, enabled: bool) -> Self {
+        self.envoy_external_address =
+            Some(X_ENVOY_EXTERNAL_ADDRESS.clone()).filter(|_| enabled);
+        self
+    }
+
+    /// Enable [Envoy mode](Self::envoy), reading the external address from a custom header.
+    ///
+    /// For meshes that rename Envoy's `X-Envoy-External-Address` header.
+    pub fn envoy_external_address_header(mut self, name: HeaderName) -> Self {
+        self.envoy_external_address = Some(name);
+        self
+    }
+
+    /// Resolve the real IP from the JSON value of a header, at the given [JSON pointer][rfc6901].
+    ///
+    /// Suits CDNs that pass connection metadata as JSON, eg. `X-Edge-Client: {"client":
+    /// {"ip": "192.0.2.60"}}` with the pointer `/client/ip`. The value must be a string holding an
+    /// IP address, optionally with a port. When the header is missing or malformed, real IP
+    /// resolution continues as if this was not set.
+    ///
+    /// Takes precedence over the forwarded chain, but not over a
+    /// [custom resolver](Self::realip_resolver). When [trusted proxies](Self::trusted_proxies)
+    /// are configured, the header is only honored if the peer is trusted.
+    ///
+    /// Requires the `json-realip` feature.
+    ///
+    /// [rfc6901]: https://datatracker.ietf.org/doc/html/rfc6901
+    #[cfg(feature = "json-realip")]
+    pub fn realip_json_header(mut self, name: HeaderName, pointer: &str) -> Self {
+        self.realip_json_header = Some((name, Cow::Owned(pointer.to_owned())));
+        self
+    }
+
+    /// Resolve the scheme, host and real IP from a header holding Base64-encoded JSON.
+    ///
+    /// Suits edge proxies that pass connection metadata in a single header, eg. `X-Edge-Meta`.
+    /// The decoded value is an object with the optional string members `scheme`, `host` and
+    /// `client_ip`, eg. `{"scheme": "https", "host": "example.com", "client_ip": "192.0.2.60"}`;
+    /// other members are ignored. `client_ip` must be an IP address, optionally with a port. When
+    /// the header is missing or malformed, or a member is absent, resolution continues as if this
+    /// was not set.
+    ///
+    /// Takes precedence over the forwarding headers. The real IP does not take precedence over a
+    /// [custom resolver](Self::realip_resolver) or the [JSON header](Self::realip_json_header).
+    /// When [trusted proxies](Self::trusted_proxies) are configured, the header is only honored
+    /// if the peer is trusted.
+    ///
+    /// Requires the `edge-metadata` feature.
+    #[cfg(feature = "edge-metadata")]
+    pub fn edge_metadata_header(mut self, name: HeaderName) -> Self {
+        self.edge_metadata_header = Some(name);
+        self
+    }
+
+    /// Only honor forwarding headers sent by trusted proxies.
+    ///
+    /// When set, forwarding headers are only consulted if the peer is a trusted proxy; this
+    /// covers the forwarded chain as well as the scheme and host from `Forwarded`,
+    /// `X-Forwarded-Proto` and `X-Forwarded-Host` and the other proxy-set headers configured
+    /// here. The chain is walked from the closest hop outwards, skipping trusted proxies, and the
+    /// first untrusted address is used as the real IP. Requests from untrusted peers report the
+    /// peer address as the real IP and resolve the scheme and host as if no forwarding headers
+    /// were sent.
+    ///
+    /// Loopback peers are also trusted unless disabled with
+    /// [`trust_loopback`](Self::trust_loopback). A
+    /// [synthetic peer address](Self::synthetic_peer_addr) is never trusted.
+    pub fn trusted_proxies(mut self, mut proxies: TrustedProxies) -> Self {
+        proxies.loopback = self.trust_loopback;
+        self.trusted_proxies = Some(proxies);
+        self
+    }
+
+    /// Set whether loopback addresses (`127.0.0.0/8` and `::1`) are
+    /// [trusted proxies](Self::trusted_proxies) without listing them.
+    ///
+    /// Defaults to true, covering the common case of a reverse proxy on the same host. Has no
+    /// effect unless trusted proxies are configured, since forwarding headers are otherwise
+    /// honored from any peer.
+    pub fn trust_loopback(mut self, trust: bool) -> Self {
+        self.trust_loopback = trust;
+
+        if let Some(ref mut proxies) = self.trusted_proxies {
+            proxies.loopback = trust;
+        }
+
+        self
+    }
+
+    /// Set whether client addresses are anonymized before they are stored in [`ConnectionInfo`].
+    ///
+    /// When enabled, the [real IP](ConnectionInfo::realip_remote_addr), the
+    /// [peer address](ConnectionInfo::remote_addr), the `for` and `by` nodes of the forwarded
+    /// chains, including the elements passed on by
+    /// [`forwarded_without_last_hop`](ConnectionInfo::forwarded_without_last_hop), and `Via`
+    /// hops that are addresses are masked to their /24 network for IPv4 and /48 network for IPv6,
+    /// so neither the getters nor the `Debug` output contain a full address. The
+    /// [peer socket address](ConnectionInfo::peer_socket_addr) is dropped and the
+    /// cached [`ResolvedClientIp`] is anonymized too. Resolution itself, including
+    /// trusted proxy checks, uses the full addresses. Disabled by default.
+    ///
+    /// Only `ConnectionInfo` is affected; [`HttpRequest::peer_addr`] and the [`PeerAddr`]
+    /// extractor read the socket address of the connection directly.
+    pub fn anonymize_ip(mut self, anonymize: bool) -> Self {
+        self.anonymize_ip = anonymize;
+        self
+    }
+
+    /// Set names of internal hops that are skipped, like trusted proxies, when walking the
+    /// forwarded chain.
+    ///
+    /// Some service meshes identify internal hops by name, eg. `for=ingress-gateway`, rather than
+    /// by address. Names are compared case-insensitively, ignoring any port. Only has an effect
+    /// when [trusted proxies](Self::trusted_proxies) are configured.
+    pub fn internal_node_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.internal_node_names = Cow::Owned(names.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Extract connection info config from app data. Check both `T` and `Data<T>`, in that order,
+    /// and fall back to the default config.
+    pub(crate) fn from_req(req: &HttpRequest) -> &Self {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<Data<Self>>().map(|d| d.as_ref()))
+            .unwrap_or(&DEFAULT_CONFIG)
+    }
+}
+
+/// Info resolved by [`ConnectionInfo::get`] with the default config, cached apart from the
+/// config-aware info so it is never mistaken for it.
+struct DefaultConnectionInfo {
+    info: ConnectionInfo,
+
+    /// Config-aware info resolved while a `Ref` to `info` was still alive.
+    configured: unsync::OnceCell<ConnectionInfo>,
+}
+
+/// Allow shared refs used as default.
+const DEFAULT_CONFIG: ConnectionInfoConfig = ConnectionInfoConfig {
+    synthetic_peer_addr: None,
+    realip_strategy: RealIpStrategy::FirstHop,
+    trusted_proxies: None,
+    internal_node_names: Cow::Borrowed(&[]),
+    host_preference: HostPreference::Forwarded,
+    prefer_http2_authority: false,
+    sni_host_fallback: false,
+    honor_forwarded_header: true,
+    require_tls_for_forwarding: false,
+    trust_loopback: true,
+    anonymize_ip: false,
+    default_scheme: Cow::Borrowed("http"),
+    geo_header: None,
+    realip_resolver: None,
+    info_resolver: None,
+    envoy_external_address: None,
+    #[cfg(feature = "json-realip")]
+    realip_json_header: None,
+    #[cfg(feature = "edge-metadata")]
+    edge_metadata_header: None,
+    #[cfg(feature = "reverse-dns")]
+    resolver: None,
+    #[cfg(feature = "registrable-domain")]
+    suffix_list: None,
+};
+
+impl Default for ConnectionInfoConfig {
+    fn default() -> Self {
+        DEFAULT_CONFIG.clone()
+    }
+}
+
+/// Reverse DNS lookup used by [`ConnectionInfo::resolve_peer_hostname`].
+///
+/// Implement this on top of the DNS client of your choice and register it with
+/// [`ConnectionInfoConfig::peer_hostname_resolver`].
+///
+/// Requires the `reverse-dns` feature.
+#[cfg(feature = "reverse-dns")]
+pub trait PeerHostnameResolver: Send + Sync {
+    /// Resolves the PTR hostname for `ip`, returning `None` if there is no record or the lookup
+    /// fails.
+    fn resolve(&self, ip: IpAddr) -> LocalBoxFuture<'static, Option<String>>;
+}
+
+#[cfg(feature = "reverse-dns")]
+#[derive(Clone)]
+struct SharedResolver(Arc<dyn PeerHostnameResolver>);
+
+#[cfg(feature = "reverse-dns")]
+impl fmt::Debug for SharedResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PeerHostnameResolver")
+    }
+}
+
+/// Public suffix lookup used by [`ConnectionInfo::registrable_domain`].
+///
+/// Actix Web does not depend on the `publicsuffix` crate or ship a copy of the list. The list
+/// changes often and a snapshot compiled into the framework would go stale between releases,
+/// silently widening cookie scopes for newly added suffixes. The authoritative data source is the
+/// [Public Suffix List](https://publicsuffix.org/list/public_suffix_list.dat) maintained by
+/// Mozilla. Implement this trait on top of a crate that loads it, eg. `publicsuffix`, keep that
+/// copy up to date, and register it with [`ConnectionInfoConfig::public_suffix_list`].
+///
+/// Requires the `registrable-domain` feature.
+///
+/// # Examples
+/// A fixed list, for apps that only serve known domains:
+/// ```
+/// use actix_web::dev::{ConnectionInfoConfig, PublicSuffixList};
+///
+/// struct KnownSuffixes;
+///
+/// impl PublicSuffixList for KnownSuffixes {
+///     fn public_suffix<'a>(&self, domain: &'a str) -> Option<&'a str> {
+///         ["co.uk", "com"]
+///             .iter()
+///             .find(|suffix| domain.ends_with(&format!(".{}", suffix)))
+///             .map(|suffix| &domain[domain.len() - suffix.len()..])
+///     }
+/// }
+///
+/// let cfg = ConnectionInfoConfig::default().public_suffix_list(KnownSuffixes);
+/// ```
+#[cfg(feature = "registrable-domain")]
+pub trait PublicSuffixList: Send + Sync {
+    /// Returns the public suffix of a lowercase domain, eg. `co.uk` for `a.b.example.co.uk`, or
+    /// `None` if it has no known suffix. The suffix must be a trailing part of `domain`.
+    fn public_suffix<'a>(&self, domain: &'a str) -> Option<&'a str>;
+}
+
+#[cfg(feature = "registrable-domain")]
+#[derive(Clone)]
+struct SharedSuffixList(Arc<dyn PublicSuffixList>);
+
+#[cfg(feature = "registrable-domain")]
+impl fmt::Debug for SharedSuffixList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PublicSuffixList")
+    }
+}
+
+/// Custom connection info resolution, registered with
+/// [`ConnectionInfoConfig::connection_info_resolver`].
+pub trait ConnectionInfoResolver: Send + Sync {
+    /// Resolves the connection info of a request.
+    fn resolve(&self, head: &RequestHead, cfg: &AppConfig) -> ConnectionInfo;
+}
+
+#[derive(Clone)]
+struct SharedInfoResolver(Arc<dyn ConnectionInfoResolver>);
+
+impl fmt::Debug for SharedInfoResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ConnectionInfoResolver")
+    }
+}
+
+/// The built-in [`ConnectionInfoResolver`], driven by a [`ConnectionInfoConfig`].
+///
+/// Custom resolvers can delegate to it and adjust the result.
+#[derive(Debug, Clone, Default)]
+pub struct DefaultResolver {
+    config: ConnectionInfoConfig,
+}
+
+impl DefaultResolver {
+    /// Constructs a resolver using the given config.
+    ///
+    /// Any [custom resolver](ConnectionInfoConfig::connection_info_resolver) set on `config` is
+    /// ignored.
+    pub fn new(config: ConnectionInfoConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl ConnectionInfoResolver for DefaultResolver {
+    fn resolve(&self, head: &RequestHead, cfg: &AppConfig) -> ConnectionInfo {
+        ConnectionInfo::new(head, cfg, &self.config)
+    }
+}
+
+#[derive(Clone)]
+struct SharedRealIpResolver(Arc<dyn Fn(&RequestHead) -> Option<IpAddr> + Send + Sync>);
+
+impl fmt::Debug for SharedRealIpResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("RealIpResolver")
+    }
+}
+
+/// Header preferred for [`ConnectionInfo::host`] when both forwarding headers are present.
+///
+/// See [`ConnectionInfoConfig::host_preference`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HostPreference {
+    /// Prefer the `host` parameter of the `Forwarded` header.
+    Forwarded,
+
+    /// Prefer the `X-Forwarded-Host` header.
+    XForwardedHost,
+}
+
+impl Default for HostPreference {
+    fn default() -> Self {
+        HostPreference::Forwarded
+    }
+}
+
+/// Set of proxy addresses and networks trusted to report the client address.
+///
+/// Addresses are compared by value, not by their textual form, so `::1` and
+/// `0:0:0:0:0:0:0:1` are the same proxy. IPv4-mapped IPv6 addresses (eg. `::ffff:10.0.0.1`)
+/// match their IPv4 equivalents.
+///
+/// See [`ConnectionInfoConfig::trusted_proxies`].
+///
+/// # Examples
+/// ```
+/// use actix_web::dev::{ConnectionInfoConfig, TrustedProxies};
+///
+/// let proxies = TrustedProxies::new()
+///     .ip("::1".parse().unwrap())
+///     .network("10.0.0.0/8".parse().unwrap());
+///
+/// assert!(proxies.contains("0:0:0:0:0:0:0:1".parse().unwrap()));
+/// assert!(proxies.contains("10.20.30.40".parse().unwrap()));
+/// assert!(!proxies.contains("192.0.2.60".parse().unwrap()));
+///
+/// let info_cfg = ConnectionInfoConfig::default().trusted_proxies(proxies);
+/// ```
+///
+/// A set can also be parsed from a comma-separated list of addresses and networks, eg. one read
+/// from an environment variable:
+/// ```
+/// use actix_web::dev::TrustedProxies;
+///
+/// let proxies: TrustedProxies = "10.0.0.0/8, ::1, 192.168.0.0/16".parse().unwrap();
+/// assert!(proxies.contains("192.168.1.1".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrustedProxies {
+    networks: Vec<IpNet>,
+    /// Set from [`ConnectionInfoConfig::trust_loopback`].
+    loopback: bool,
+}
+
+impl TrustedProxies {
+    /// Constructs an empty set that trusts no proxies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Trust a single proxy address.
+    pub fn ip(self, ip: IpAddr) -> Self {
+        self.network(IpNet::from(canonical_ip(ip)))
+    }
+
+    /// Trust all proxies within a network.
+    ///
+    /// [`IpNet`] is re-exported as `actix_web::dev::IpNet`; a whole set can also be parsed from a
+    /// string.
+    pub fn network(mut self, net: IpNet) -> Self {
+        self.networks.push(net);
+        self
+    }
+
+    /// Returns true if `ip` is a trusted proxy address.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        self.network_of(ip).is_some()
+    }
+
+    /// Returns the first configured network containing `ip`.
+    fn network_of(&self, ip: IpAddr) -> Option<&IpNet> {
+        let ip = canonical_ip(ip);
+        let loopback = LOOPBACK_NETWORKS.iter().filter(|_| self.loopback);
+
+        self.networks
+            .iter()
+            .chain(loopback)
+            .find(|net| net.contains(&ip))
+    }
+
+    /// Walks a forwarded chain, given the immediate peer's IP, and returns the client node.
+    ///
+    /// Trusted addresses and nodes named in `internal_names` are skipped.
+    fn client<'a>(
+        &self,
+        peer: Option<IpAddr>,
+        chain: &[&'a str],
+        internal_names: &[String],
+    ) -> Option<&'a str> {
+        // forwarded values sent by untrusted peers are ignored
+        if !peer.map_or(false, |peer| self.contains(peer)) {
+            return None;
+        }
+
+        let mut client = None;
+
+        for &node in chain.iter().rev() {
+            client = Some(node);
+
+            match parse_node_ip(node) {
+                Some(ip) if self.contains(ip) => {}
+                Some(_) => break,
+                None => {
+                    let (name, _) = split_host_port(unquote(node));
+
+                    if !internal_names
+                        .iter()
+                        .any(|internal| internal.eq_ignore_ascii_case(name))
+                    {
+                        break;
+                    }
+                }
+            }
+        }
+
+        client
+    }
+}
+
+impl str::FromStr for TrustedProxies {
+    type Err = ParseTrustedProxiesError;
+
+    /// Parses a comma-separated list of IP addresses and CIDR networks.
+    ///
+    /// Whitespace around entries is ignored, as are empty entries; an empty string yields an empty
+    /// set.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .try_fold(TrustedProxies::new(), |proxies, entry| {
+                if let Ok(net) = entry.parse::<IpNet>() {
+                    Ok(proxies.network(net))
+                } else if let Ok(ip) = entry.parse::<IpAddr>() {
+                    Ok(proxies.ip(ip))
+                } else {
+                    Err(ParseTrustedProxiesError {
+                        entry: entry.to_owned(),
+                    })
+                }
+            })
+    }
+}
+
+/// Error returned when parsing a [`TrustedProxies`] list fails.
+#[derive(Debug, Display, Error, Clone, PartialEq, Eq)]
+#[display(fmt = "Invalid trusted proxy address or network: {}", entry)]
+pub struct ParseTrustedProxiesError {
+    entry: String,
+}
+
+impl ParseTrustedProxiesError {
+    /// The list entry that is neither an IP address nor a CIDR network.
+    pub fn entry(&self) -> &str {
+        &self.entry
+    }
+}
+
+/// Error returned by [`ConnectionInfo::replace_cached`] when the cached info is still borrowed;
+/// responds with a 500 Internal Server Error.
+#[derive(Debug, Display, Error, Clone, Copy, PartialEq, Eq)]
+#[display(fmt = "Cached connection info is borrowed")]
+#[non_exhaustive]
+pub struct ConnectionInfoBorrowed;
+
+impl ResponseError for ConnectionInfoBorrowed {}
+
+/// Error returned by [`ConnectionInfo::validate_hop_count`] when the forwarded chain is longer
+/// than expected; responds with a 400 Bad Request.
+#[derive(Debug, Display, Error, Clone, PartialEq, Eq)]
+#[display(
+    fmt = "Forwarded chain has {} hops, more than the maximum of {}",
+    hops,
+    max
+)]
+pub struct TooManyHops {
+    hops: usize,
+    max: usize,
+}
+
+impl TooManyHops {
+    /// Number of entries in the forwarded chain.
+    pub fn hops(&self) -> usize {
+        self.hops
+    }
+
+    /// Maximum number of entries that was expected.
+    pub fn max(&self) -> usize {
+        self.max
+    }
+}
+
+impl ResponseError for TooManyHops {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+/// Selects the entry of the `Forwarded` or `X-Forwarded-For` chain reported as the real IP.
+///
+/// See [`ConnectionInfoConfig::realip_strategy`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RealIpStrategy {
+    /// Use the leftmost entry; the originating client according to the proxies.
+    FirstHop,
+
+    /// Use the rightmost entry; the sender closest to this server.
+    ///
+    /// Suited to fully internal chains where every hop is trusted and the immediate sender is of
+    /// interest.
+    LastHop,
+}
+
+impl Default for RealIpStrategy {
+    fn default() -> Self {
+        RealIpStrategy::FirstHop
+    }
+}
+
+/// Extractor for the most commonly used parts of [`ConnectionInfo`].
+///
+/// `client_ip` is the [real IP](ConnectionInfo::realip_remote_addr) parsed as an `IpAddr`; it is
+/// `None` when unavailable or when the resolved value is not an IP address (eg. `unknown`).
+///
+/// # Examples
+/// ```
+/// # use actix_web::Responder;
+/// use actix_web::dev::Conn;
+///
+/// async fn handler(conn: Conn) -> impl Responder {
+///     match conn.client_ip {
+///         Some(ip) => format!("{}://{} requested by {}", conn.scheme, conn.host, ip),
+///         None => format!("{}://{} requested", conn.scheme, conn.host),
+///     }
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conn {
+    /// Resolved scheme. See [`ConnectionInfo::scheme`].
+    pub scheme: String,
+
+    /// Resolved host. See [`ConnectionInfo::host`].
+    pub host: String,
+
+    /// Resolved client IP address. See [`ConnectionInfo::realip_remote_addr`].
+    pub client_ip: Option<IpAddr>,
+}
+
+impl FromRequest for Conn {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let info = req.connection_info();
+
+        ok(Conn {
+            scheme: info.scheme().to_owned(),
+            host: info.host().to_owned(),
+            client_ip: info.realip_remote_addr().and_then(parse_node_ip),
+        })
+    }
+}
+
+/// Extractor summarizing whether the request opens a tunnel.
+///
+/// `is_connect` is true for `CONNECT` requests. `upgrade_protocol` is the first protocol listed in
+/// the `Upgrade` header, eg. `websocket`, when the `Connection` header requests an upgrade; an
+/// `Upgrade` header without it is ignored.
+///
+/// # Examples
+/// ```
+/// # use actix_web::Responder;
+/// use actix_web::dev::TunnelInfo;
+///
+/// async fn handler(tunnel: TunnelInfo) -> impl Responder {
+///     match (tunnel.is_connect, tunnel.upgrade_protocol) {
+///         (true, _) => "tunneling".to_owned(),
+///         (false, Some(protocol)) => format!("switching to {}", protocol),
+///         (false, None) => "plain request".to_owned(),
+///     }
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TunnelInfo {
+    /// Whether the request method is `CONNECT`.
+    pub is_connect: bool,
+
+    /// Protocol the client asked to upgrade to.
+    pub upgrade_protocol: Option<String>,
+}
+
+impl FromRequest for TunnelInfo {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let head = req.head();
+
+        let upgrade_protocol = head
+            .headers
+            .get(&header::UPGRADE)
+            .filter(|_| head.upgrade())
+            .and_then(header_str)
+            // "websocket, h2c" => "websocket"
+            .and_then(|val| {
+                val.split(',')
+                    .map(str::trim)
+                    .find(|proto| !proto.is_empty())
+            })
+            .map(ToOwned::to_owned);
+
+        ok(TunnelInfo {
+            is_connect: head.method == Method::CONNECT,
+            upgrade_protocol,
+        })
+    }
+}
+
+/// Extractor for the absolute URI of the request.
+///
+/// Combines the resolved [scheme](ConnectionInfo::scheme) and [host](ConnectionInfo::host) with
+/// the path and query of the request URI, eg. for signing requests or canonical-URL redirects.
+/// The path and query are kept exactly as received, including percent-encoding. Responds with a
+/// 400 Bad Request if the result is not a valid URI.
+///
+/// # Examples
+/// ```
+/// # use actix_web::Responder;
+/// use actix_web::dev::AbsoluteUri;
+///
+/// async fn handler(uri: AbsoluteUri) -> impl Responder {
+///     format!("canonical URL is {}", uri.0)
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AbsoluteUri(pub Uri);
+
+impl AbsoluteUri {
+    fn from_info(info: &ConnectionInfo, uri: &Uri) -> Result<Self, ConnectionInfoError> {
+        let path_and_query = uri.path_and_query().map_or("/", PathAndQuery::as_str);
+
+        Uri::builder()
+            .scheme(info.scheme())
+            .authority(info.host())
+            .path_and_query(path_and_query)
+            .build()
+            .map(AbsoluteUri)
+            .map_err(|_| ConnectionInfoError::InvalidAbsoluteUri)
+    }
+}
+
+impl FromRequest for AbsoluteUri {
+    type Error = ConnectionInfoError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match AbsoluteUri::from_info(&req.connection_info(), req.uri()) {
+            Ok(uri) => ok(uri),
+            Err(e) => {
+                log::debug!("Failed during AbsoluteUri extractor: {}", e);
+                err(e)
+            }
+        }
+    }
+}
+
+/// Extractor for the request host, which fails if the server certificate does not cover it.
+///
+/// With HTTP/2 connection coalescing, a client may reuse a connection for any host the server's
+/// certificate is valid for. Requests for other hosts are rejected with a
+/// `421 Misdirected Request` so the client retries on a new connection, as described in
+/// [RFC 7540 §9.1.2][rfc7540-912].
+///
+/// The [host](ConnectionInfo::host), without its port, is compared case-insensitively against the
+/// names set in [`CertifiedHostConfig`] app data. Wildcard names (eg. `*.example.com`) cover a
+/// single label. No hosts are covered when the config is not set.
+///
+/// [rfc7540-912]: https://datatracker.ietf.org/doc/html/rfc7540#section-9.1.2
+///
+/// # Examples
+/// ```
+/// use actix_web::{dev::{CertifiedHost, CertifiedHostConfig}, web, App, Responder};
+///
+/// async fn handler(host: CertifiedHost) -> impl Responder {
+///     format!("Hello, {}!", host.0)
+/// }
+///
+/// let app = App::new()
+///     .app_data(CertifiedHostConfig::new(vec!["example.com", "*.example.com"]))
+///     .route("/", web::get().to(handler));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertifiedHost(pub String);
+
+impl FromRequest for CertifiedHost {
+    type Error = MisdirectedRequest;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = CertifiedHostConfig;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let info = req.connection_info();
+        let covered =
+            CertifiedHostConfig::from_req(req).map_or(false, |cfg| cfg.covers(info.host()));
+
+        if covered {
+            ok(CertifiedHost(info.host().to_owned()))
+        } else {
+            log::debug!(
+                "Failed during CertifiedHost extractor: host {:?} is not covered by the certificate",
+                info.host()
+            );
+            err(MisdirectedRequest)
+        }
+    }
+}
+
+/// Names covered by the server certificate, for the [`CertifiedHost`] extractor.
+///
+/// Typically the DNS names and IP addresses of the certificate's subject alternative names.
+#[derive(Debug, Clone, Default)]
+pub struct CertifiedHostConfig {
+    names: Vec<String>,
+}
+
+impl CertifiedHostConfig {
+    /// Constructs config covering the given names.
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            names: names.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Returns true if `host`, ignoring any port, is covered by one of the names.
+    fn covers(&self, host: &str) -> bool {
+        let (hostname, _) = split_host_port(host);
+        let hostname = hostname.trim_end_matches('.');
+
+        self.names.iter().any(|name| {
+            let name = name.trim_end_matches('.');
+
+            match name.strip_prefix("*.") {
+                Some(parent) => match hostname.find('.') {
+                    Some(idx) => idx > 0 && hostname[idx + 1..].eq_ignore_ascii_case(parent),
+                    None => false,
+                },
+                None => hostname.eq_ignore_ascii_case(name),
+            }
+        })
+    }
+
+    /// Extract config from app data. Check both `T` and `Data<T>`, in that order.
+    fn from_req(req: &HttpRequest) -> Option<&Self> {
+        req.app_data::<Self>()
+            .or_else(|| req.app_data::<Data<Self>>().map(|d| d.as_ref()))
+    }
+}
+
+/// Error returned by the [`CertifiedHost`] extractor when the host is not covered by the
+/// certificate; responds with a 421 Misdirected Request.
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+#[display(fmt = "Host is not covered by the server certificate")]
+pub struct MisdirectedRequest;
+
+impl ResponseError for MisdirectedRequest {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::MISDIRECTED_REQUEST
+    }
+}
+
+/// Extractor for the client IP formatted for access logs.
+///
+/// IPv4 addresses are written plainly and IPv6 addresses in brackets, both without a port. Uses
+/// `-` when the [real IP](ConnectionInfo::realip_remote_addr) is unavailable or not an IP address,
+/// following the Common Log Format convention.
+///
+/// # Examples
+/// ```
+/// # use actix_web::Responder;
+/// use actix_web::dev::LogClientIp;
+///
+/// async fn handler(client_ip: LogClientIp) -> impl Responder {
+///     log::info!("request from {}", client_ip);
+///     "Hello!"
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Display)]
+#[display(fmt = "{}", _0)]
+pub struct LogClientIp(pub String);
+
+impl LogClientIp {
+    /// Unwrap into inner `String` value.
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+
+    fn from_info(info: &ConnectionInfo) -> Self {
+        let formatted = match info.realip_remote_addr().and_then(parse_node_ip) {
+            Some(IpAddr::V4(ip)) => ip.to_string(),
+            Some(IpAddr::V6(ip)) => format!("[{}]", ip),
+            None => "-".to_owned(),
+        };
+
+        LogClientIp(formatted)
+    }
+}
+
+impl FromRequest for LogClientIp {
+    type Error = Infallible;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ok(LogClientIp::from_info(&req.connection_info()))
+    }
+}
+
+/// Extractor for a value stored in connection data.
+///
+/// Connection data is set from a
+/// [`HttpServer::on_connect_conn_data`](crate::HttpServer::on_connect_conn_data) callback, eg. by
+/// custom transports recording TLS or PROXY protocol details, and is shared by all requests on that
+/// connection. Values set by an [`HttpServer::on_connect`](crate::HttpServer::on_connect) callback
+/// only reach the extensions of the first request and are not extracted. The value is cloned out
+/// for each extraction, so prefer cheaply cloneable types. Responds with a 500 Internal Server
+/// Error if no value of type `T` was stored.
+///
+/// # Examples
+/// ```
+/// use std::any::Any;
+/// use actix_web::{dev::{ConnectionData, Extensions}, Responder};
+///
+/// #[derive(Clone)]
+/// struct ProxyProtocolSource(std::net::SocketAddr);
+///
+/// // passed to `HttpServer::on_connect_conn_data`
+/// fn on_connect_conn_data(_conn: &dyn Any, conn_data: &mut Extensions) {
+///     // parsed from the PROXY protocol preamble in a real server
+///     conn_data.insert(ProxyProtocolSource("192.0.2.60:4711".parse().unwrap()));
+/// }
+///
+/// async fn handler(source: ConnectionData<ProxyProtocolSource>) -> impl Responder {
+///     let ProxyProtocolSource(addr) = source.into_inner();
+///     format!("connection proxied for {}", addr)
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionData<T>(pub T);
+
+impl<T> ConnectionData<T> {
+    /// Unwrap into inner `T` value.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for ConnectionData<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Clone + 'static> FromRequest for ConnectionData<T> {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match req.head().conn_data().and_then(Extensions::get::<T>) {
+            Some(data) => ok(ConnectionData(data.clone())),
+            None => {
+                log::debug!(
+                    "Failed to construct ConnectionData extractor. \
+                     Request path: {:?} (type: {})",
+                    req.path(),
+                    type_name::<T>(),
+                );
+                err(ErrorInternalServerError(
+                    "Connection data is not set, to set use HttpServer::on_connect_conn_data()",
+                ))
+            }
+        }
+    }
+}
+
+/// Values forced by [`TestRequest`](crate::test::TestRequest), applied after resolution.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConnectionInfoOverrides {
+    pub(crate) scheme: Option<String>,
+    pub(crate) host: Option<String>,
+    pub(crate) realip: Option<String>,
+}
+
+impl ConnectionInfoOverrides {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.scheme.is_none() && self.host.is_none() && self.realip.is_none()
+    }
+
+    /// Resolves the info of a test request with the config from its app data, applies the forced
+    /// values and caches the result, so resolution itself never looks for overrides.
+    pub(crate) fn resolve_cached(
+        &self,
+        req: &RequestHead,
+        cfg: &AppConfig,
+        app_data: &Extensions,
+    ) {
+        let info_cfg = app_data
+            .get::<ConnectionInfoConfig>()
+            .or_else(|| {
+                app_data
+                    .get::<Data<ConnectionInfoConfig>>()
+                    .map(|d| d.as_ref())
+            })
+            .unwrap_or(&DEFAULT_CONFIG);
+
+        let mut info = ConnectionInfo::resolve(req, cfg, info_cfg);
+
+        if let Some(scheme) = &self.scheme {
+            info.scheme = scheme.clone();
+        }
+
+        if let Some(host) = &self.host {
+            // fully qualified names are equivalent to their relative form for routing
+            let (host, raw_host) = match strip_fqdn_dot(host) {
+                Some(normalized) => (normalized, Some(host.clone())),
+                None => (host.clone(), None),
+            };
+            info.host = host;
+            info.raw_host = raw_host;
+        }
+
+        if let Some(realip) = &self.realip {
+            info.realip_remote_addr = Some(realip.clone());
+        }
+
+        // forced values are anonymized like resolved ones
+        if info_cfg.anonymize_ip {
+            info.anonymize_addresses();
+        }
+
+        info.insert_cached(req);
+    }
+}
+
+/// Connection data recording when a connection was accepted.
+///
+/// Set this from a [`HttpServer::on_connect`](crate::HttpServer::on_connect) callback to make it
+/// available through [`ConnectionInfo::connection_established_at`].
+///
+/// # Examples
+/// ```
+/// use std::{any::Any, time::Instant};
+/// use actix_web::dev::{ConnectionEstablishedAt, Extensions};
+///
+/// fn on_connect(_conn: &dyn Any, data: &mut Extensions) {
+///     data.insert(ConnectionEstablishedAt(Instant::now()));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ConnectionEstablishedAt(pub Instant);
+
+/// Connection data recording the peer address of a QUIC connection.
+///
+/// HTTP/3 requests are not received on a TCP socket, so the request head has no peer address.
+/// Set this from the QUIC transport's connection callback to populate
+/// [`HttpRequest::peer_addr`], [`PeerAddr`] and [`ConnectionInfo::remote_addr`]. A socket peer
+/// address takes precedence when both are set.
+///
+/// # Examples
+/// ```
+/// use std::net::SocketAddr;
+/// use actix_web::dev::{Extensions, QuicPeerAddr};
+///
+/// fn on_quic_connect(remote: SocketAddr, data: &mut Extensions) {
+///     data.insert(QuicPeerAddr(remote));
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct QuicPeerAddr(pub SocketAddr);
+
+/// Peer address of the connection, from the request head or QUIC connection data.
+pub(crate) fn connection_peer_addr(req: &RequestHead) -> Option<SocketAddr> {
+    req.peer_addr
+        .or_else(|| ConnectionConstants::of(req).quic_peer_addr)
+}
+
+/// Inputs to connection info that are constant for a connection.
+///
+/// When an on-connect callback is set, [`HttpServer`](crate::HttpServer) records these in the
+/// connection data once per connection, after the callback, so requests on a keep-alive
+/// connection do not look up each connection data type again.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ConnectionConstants {
+    tls: bool,
+    sni: Option<String>,
+    transport: Option<Transport>,
+    quic_peer_addr: Option<SocketAddr>,
+    established_at: Option<Instant>,
+    http2_negotiation: Option<Http2Negotiation>,
+}
+
+impl ConnectionConstants {
+    /// Reads each input from the first of `sources` holding it.
+    fn from_data(sources: &[&Extensions]) -> Self {
+        fn find<'a, T: 'static>(sources: &[&'a Extensions]) -> Option<&'a T> {
+            sources.iter().find_map(|data| data.get::<T>())
+        }
+
+        let tls = find::<TlsInfo>(sources);
+
+        ConnectionConstants {
+            tls: tls.is_some(),
+            sni: tls.and_then(|tls| tls.sni.clone()),
+            transport: find::<Transport>(sources).copied(),
+            quic_peer_addr: find::<QuicPeerAddr>(sources).map(|addr| addr.0),
+            established_at: find::<ConnectionEstablishedAt>(sources).map(|at| at.0),
+            http2_negotiation: find::<Http2Negotiation>(sources).copied(),
+        }
+    }
+
+    /// Computes the constants from the data set by the on-connect callbacks and stores them in
+    /// the connection data.
+    ///
+    /// Values in the connection data take precedence over those merged into request extensions.
+    pub(crate) fn record(data: &Extensions, conn_data: &mut Extensions) {
+        let constants = Self::from_data(&[conn_data, data]);
+        conn_data.insert(constants);
+    }
+
+    /// Returns the constants of the connection a request arrived on.
+    ///
+    /// They are computed here when the server did not record them, and read from the request
+    /// extensions for requests built without a connection, eg. by `TestRequest`.
+    fn of(req: &RequestHead) -> Cow<'_, Self> {
+        match req.conn_data() {
+            Some(data) => match data.get::<Self>() {
+                Some(constants) => Cow::Borrowed(constants),
+                None => Cow::Owned(Self::from_data(&[data])),
+            },
+            None => Cow::Owned(Self::from_data(&[&req.extensions()])),
+        }
+    }
+}
+
+/// Request extension recording the app's config, for code that only sees the request head.
+///
+/// Inserted by the app for every request when it is served over TLS or has a
+/// [`ConnectionInfoConfig`] in its app data.
+#[derive(Clone)]
+pub(crate) struct AppHeadConfig {
+    secure: bool,
+    info_cfg: Option<Rc<ConnectionInfoConfig>>,
+}
+
+impl AppHeadConfig {
+    /// Returns `None` when the app has nothing to record.
+    pub(crate) fn new(cfg: &AppConfig, app_data: &Extensions) -> Option<Self> {
+        let info_cfg = app_data
+            .get::<ConnectionInfoConfig>()
+            .or_else(|| {
+                app_data
+                    .get::<Data<ConnectionInfoConfig>>()
+                    .map(|d| d.as_ref())
+            })
+            .map(|info_cfg| Rc::new(info_cfg.clone()));
+
+        if cfg.secure() || info_cfg.is_some() {
+            Some(AppHeadConfig {
+                secure: cfg.secure(),
+                info_cfg,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether the scheme resolved by [`head_is_secure`] is secure, cached in request extensions.
+struct HeadSecure(bool);
+
+/// Config that honors no forwarding headers, for requests whose app config is not known.
+const NO_FORWARDING_CONFIG: ConnectionInfoConfig = ConnectionInfoConfig {
+    trusted_proxies: Some(TrustedProxies {
+        networks: Vec::new(),
+        loopback: false,
+    }),
+    trust_loopback: false,
+    ..DEFAULT_CONFIG
+};
+
+/// Returns true if the request's scheme is secure, for code that only sees the request head, such
+/// as guards.
+///
+/// Uses the connection info already resolved for the request, if any. Otherwise the scheme is
+/// resolved with the [`ConnectionInfoConfig`] recorded by the app; without one, forwarding headers
+/// are ignored. The result is cached apart from the connection info so a later
+/// [`HttpRequest::connection_info`] still applies config set on resources and scopes.
+pub(crate) fn head_is_secure(req: &RequestHead) -> bool {
+    let app_cfg = {
+        let extensions = req.extensions();
+
+        if let Some(info) = extensions.get::<ConnectionInfo>() {
+            return info.is_secure();
+        }
+
+        if let Some(HeadSecure(secure)) = extensions.get() {
+            return *secure;
+        }
+
+        extensions.get::<AppHeadConfig>().cloned()
+    };
+
+    let (secure, info_cfg) = match app_cfg {
+        Some(app_cfg) => (app_cfg.secure, app_cfg.info_cfg),
+        None => (false, None),
+    };
+
+    // host and address are only used when the request names no host; neither affects the scheme
+    let cfg = AppConfig::new(
+        secure,
+        String::new(),
+        SocketAddr::from(([127, 0, 0, 1], 8080)),
+    );
+
+    let info = match info_cfg {
+        Some(info_cfg) => ConnectionInfo::resolve(req, &cfg, &info_cfg),
+        None => ConnectionInfo::new(req, &cfg, &NO_FORWARDING_CONFIG),
+    };
+
+    let secure = info.is_secure();
+    req.extensions_mut().insert(HeadSecure(secure));
+    secure
+}
+
+/// Connection data recording the transport a connection was accepted on.
+///
+/// Set this from a [`HttpServer::on_connect`](crate::HttpServer::on_connect) callback to make it
+/// available through [`ConnectionInfo::transport`].
+///
+/// # Examples
+/// ```
+/// use std::any::Any;
+/// use actix_web::dev::{Extensions, Transport};
+///
+/// fn on_connect(conn: &dyn Any, data: &mut Extensions) {
+///     # #[cfg(unix)]
+///     if conn.is::<actix_rt::net::UnixStream>() {
+///         data.insert(Transport::Unix);
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Transport {
+    /// TCP connection.
+    Tcp,
+
+    /// Unix domain socket connection.
+    Unix,
+
+    /// QUIC connection.
+    Quic,
+
+    /// Transport could not be determined.
+    Unknown,
+}
+
+impl Default for Transport {
+    fn default() -> Self {
+        Transport::Unknown
+    }
+}
+
+/// Connection data recording how HTTP/2 was negotiated.
+///
+/// Set this from a [`HttpServer::on_connect`](crate::HttpServer::on_connect) callback to make it
+/// available through [`ConnectionInfo::http2_negotiation`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Http2Negotiation {
+    /// Cleartext HTTP/2 started with prior knowledge (h2c).
+    PriorKnowledge,
+
+    /// HTTP/2 selected through TLS ALPN (h2).
+    Alpn,
+
+    /// HTTP/2 reached through an HTTP/1.1 `Upgrade: h2c` request.
+    Upgrade,
+}
+
+/// Connection data describing a TLS connection accepted by this server.
+///
+/// Its presence marks the connection as encrypted. Set this from a
+/// [`HttpServer::on_connect`](crate::HttpServer::on_connect) callback, using the negotiated
+/// session of your TLS acceptor, to make it available through eg.
+/// [`ConnectionInfo::host_matches_sni`].
+///
+/// # Examples
+/// ```
+/// use std::any::Any;
+/// use actix_web::dev::{Extensions, TlsInfo};
+///
+/// fn on_connect(_conn: &dyn Any, data: &mut Extensions) {
+///     // read from the TLS stream in a real server
+///     let sni = Some("rust-lang.org".to_owned());
+///
+///     let alpn = Some("h2".to_owned());
+///
+///     data.insert(TlsInfo { sni, alpn });
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TlsInfo {
+    /// Server name sent by the client in the TLS handshake, if any.
+    pub sni: Option<String>,
+
+    /// Application protocol negotiated through ALPN, eg. `h2`, if any.
+    pub alpn: Option<String>,
+}
+
+/// Extractor for peer's socket address.
+///
+/// Also see [`HttpRequest::peer_addr`]. Falls back to the
+/// [synthetic peer address](ConnectionInfoConfig::synthetic_peer_addr) if one is configured.
+///
+/// # Examples
+/// ```
+/// # use actix_web::Responder;
+/// use actix_web::dev::PeerAddr;
+///
+/// async fn handler(peer_addr: PeerAddr) -> impl Responder {
+///     let socket_addr = peer_addr.0;
+///     socket_addr.to_string()
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Display)]
+#[display(fmt = "{}", _0)]
+pub struct PeerAddr(pub SocketAddr);
+
+impl PeerAddr {
+    /// Unwrap into inner `SocketAddr` value.
+    pub fn into_inner(self) -> SocketAddr {
+        self.0
+    }
+
+    /// Returns true if the peer's IP address is within the given network.
+    ///
+    /// [`IpNet`] is re-exported as `actix_web::dev::IpNet`, so callers do not need to depend on the
+    /// `ipnet` crate.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_web::dev::{IpNet, PeerAddr};
+    ///
+    /// let net: IpNet = "10.0.0.0/8".parse().unwrap();
+    /// assert!(PeerAddr("10.1.2.3:80".parse().unwrap()).is_in(&net));
+    /// assert!(!PeerAddr("192.0.2.1:80".parse().unwrap()).is_in(&net));
+    /// ```
+    pub fn is_in(&self, net: &IpNet) -> bool {
+        net.contains(&self.0.ip())
+    }
+
+    /// Returns true if the peer's IP address is a loopback address.
+    pub fn is_loopback(&self) -> bool {
+        self.0.ip().is_loopback()
+    }
+}
+
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+#[display(fmt = "Missing peer address")]
+pub struct MissingPeerAddr;
+
+impl ResponseError for MissingPeerAddr {}
+
+impl FromRequest for PeerAddr {
+    type Error = MissingPeerAddr;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let addr = req
+            .peer_addr()
+            .or(ConnectionInfoConfig::from_req(req).synthetic_peer_addr);
+
+        match addr {
+            Some(addr) => ok(PeerAddr(addr)),
+            None => {
+                // expected on transports without peer addresses, eg. Unix domain sockets, so this
+                // is not logged as an error; configure a synthetic peer address to avoid it
+                log::debug!(
+                    "Failed to construct PeerAddr extractor: missing peer address. \
+                     Request path: {:?}",
+                    req.path()
+                );
+                err(MissingPeerAddr)
+            }
+        }
+    }
+}
+
+/// Extractor for the client's IP address that fails if it cannot be determined.
+///
+/// The address is taken from [`ConnectionInfo::realip_remote_addr`] so the same
+/// [security caveats](ConnectionInfo::realip_remote_addr#security) apply. Responds with a 400 Bad
+/// Request if no address is available or it is not a valid IP address.
+///
+/// # Examples
+/// ```
+/// # use actix_web::Responder;
+/// use actix_web::dev::RequiredClientIp;
+///
+/// async fn handler(client_ip: RequiredClientIp) -> impl Responder {
+///     format!("Hello, {}!", client_ip)
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Display)]
+#[display(fmt = "{}", _0)]
+pub struct RequiredClientIp(pub IpAddr);
+
+impl RequiredClientIp {
+    /// Unwrap into inner `IpAddr` value.
+    pub fn into_inner(self) -> IpAddr {
+        self.0
+    }
+}
+
+/// Extractor for the client IP cached in request extensions during [`ConnectionInfo`] resolution.
+///
+/// The value is inserted once, alongside the cached `ConnectionInfo`, so layers that only need
+/// the client address can read it without parsing headers again. It is the same address as
+/// [`RequiredClientIp`]; resolution is triggered if it has not happened yet. Responds with a
+/// 400 Bad Request if no valid client IP is available.
+///
+/// # Examples
+/// ```
+/// # use actix_web::Responder;
+/// use actix_web::dev::ResolvedClientIp;
+///
+/// async fn handler(client_ip: ResolvedClientIp) -> impl Responder {
+///     format!("Hello, {}!", client_ip)
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Display)]
+#[display(fmt = "{}", _0)]
+pub struct ResolvedClientIp(pub IpAddr);
+
+impl ResolvedClientIp {
+    fn cached(req: &HttpRequest) -> Result<Self, ClientIpError> {
+        // ensures the value has been cached
+        drop(req.connection_info());
+
+        match req.extensions().get::<ResolvedClientIp>() {
+            Some(ip) => Ok(*ip),
+            // a real IP that did not parse as an IP address, eg. an obfuscated node, is not cached
+            None if req.connection_info().realip_remote_addr().is_some() => {
+                Err(ClientIpError::Invalid)
+            }
+            None => Err(ClientIpError::Missing),
+        }
+    }
+}
+
+impl FromRequest for ResolvedClientIp {
+    type Error = ClientIpError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match ResolvedClientIp::cached(req) {
+            Ok(ip) => ok(ip),
+            Err(e) => err(e),
+        }
+    }
+}
+
+/// Extractor for a stable key derived from the client IP, eg. for sticky routing.
+///
+/// The [resolved client IP](ResolvedClientIp) is hashed, ignoring any port, with a fixed hash
+/// function so the same client maps to the same key in every process; suitable for
+/// consistent-hashing a backend pool. IPv4-mapped IPv6 addresses yield the same key as their IPv4
+/// equivalents. Configure [trusted proxies](ConnectionInfoConfig::trusted_proxies) to keep
+/// clients from choosing their key with forwarding headers. Responds with a 400 Bad Request if no
+/// valid client IP is available.
+///
+/// # Examples
+/// ```
+/// # use actix_web::Responder;
+/// use actix_web::dev::StickyKey;
+///
+/// async fn handler(key: StickyKey) -> impl Responder {
+///     let backends = ["10.0.0.1", "10.0.0.2", "10.0.0.3"];
+///     backends[(key.0 % backends.len() as u64) as usize]
+/// }
+/// # let _svc = actix_web::web::to(handler);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StickyKey(pub u64);
+
+impl StickyKey {
+    fn from_ip(ip: IpAddr) -> Self {
+        StickyKey(match canonical_ip(ip) {
+            IpAddr::V4(ip) => fnv1a(ip.octets().iter().copied()),
+            IpAddr::V6(ip) => fnv1a(ip.octets().iter().copied()),
+        })
+    }
+}
+
+impl FromRequest for StickyKey {
+    type Error = ClientIpError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match ResolvedClientIp::cached(req) {
+            Ok(ip) => ok(StickyKey::from_ip(ip.0)),
+            Err(e) => err(e),
+        }
+    }
+}
+
+/// Errors that can occur when extracting a [`RequiredClientIp`], [`ResolvedClientIp`] or
+/// [`StickyKey`].
+#[derive(Debug, Display, Error)]
+#[non_exhaustive]
+pub enum ClientIpError {
+    /// Neither forwarding headers nor the peer address provided a client address.
+    #[display(fmt = "Missing client IP address")]
+    Missing,
+
+    /// The resolved client address could not be parsed as an IP address.
+    #[display(fmt = "Invalid client IP address")]
+    Invalid,
+}
+
+impl ResponseError for ClientIpError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+}
+
+impl FromRequest for RequiredClientIp {
+    type Error = ClientIpError;
+    type Future = Ready<Result<Self, Self::Error>>;
+    type Config = ();
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let info = req.connection_info();
+
+        let res = match info.realip_remote_addr() {
+            Some(addr) => parse_node_ip(addr)
+                .map(RequiredClientIp)
+                .ok_or(ClientIpError::Invalid),
+            None => Err(ClientIpError::Missing),
+        };
+
+        match res {
+            Ok(ip) => ok(ip),
+            Err(e) => err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, ptr};
+
+    use super::*;
+    use crate::test::TestRequest;
+
+    thread_local! {
+        /// Number of times connection info was resolved on this thread.
+        pub(super) static PARSE_COUNT: Cell<usize> = Cell::new(0);
+    }
+
+    const X_FORWARDED_FOR: &str = "x-forwarded-for";
+    const X_FORWARDED_HOST: &str = "x-forwarded-host";
+    const X_FORWARDED_PROTO: &str = "x-forwarded-proto";
+    const X_FORWARDED_SCHEME: &str = "x-forwarded-scheme";
+    const X_FORWARDED_SSL: &str = "x-forwarded-ssl";
+    const X_FORWARDED_PORT: &str = "x-forwarded-port";
+
+    #[test]
+    fn info_default() {
+        let req = TestRequest::default().to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.host(), "localhost:8080");
+    }
+
+    #[test]
+    fn host_header() {
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.realip_remote_addr(), None);
+    }
+
+    #[test]
+    fn forwarded_empty_pairs() {
+        for forwarded in &[
+            "for=192.0.2.60;",
+            "for=192.0.2.60,",
+            "for=192.0.2.60;;proto=https",
+            "for=192.0.2.60,,proto=https",
+            ";, for=192.0.2.60 ; ;proto=https;,",
+            "for=;for=192.0.2.60;proto=\"\"",
+        ] {
+            let req = TestRequest::default()
+                .insert_header((header::FORWARDED, *forwarded))
+                .to_http_request();
+            let info = req.connection_info();
+            assert_eq!(
+                info.realip_remote_addr(),
+                Some("192.0.2.60"),
+                "{}",
+                forwarded
+            );
+            assert_eq!(info.hop_count(), 1, "{}", forwarded);
+        }
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=192.0.2.60;;proto=https;"))
+            .to_http_request();
+        assert_eq!(req.connection_info().scheme(), "https");
+    }
+
+    #[test]
+    fn x_forwarded_for_header() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+    }
+
+    #[test]
+    fn x_forwarded_host_header() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_HOST, "192.0.2.60"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.host(), "192.0.2.60");
+        assert_eq!(info.realip_remote_addr(), None);
+    }
+
+    #[test]
+    fn x_forwarded_proto_header() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+    }
+
+    #[test]
+    fn forwarded_header() {
+        let req = TestRequest::default()
+            .insert_header((
+                header::FORWARDED,
+                "for=192.0.2.60; proto=https; by=203.0.113.43; host=rust-lang.org",
+            ))
+            .to_http_request();
+
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+
+        let req = TestRequest::default()
+            .insert_header((
+                header::FORWARDED,
+                "for=192.0.2.60; proto=https; by=203.0.113.43; host=rust-lang.org",
+            ))
+            .to_http_request();
+
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+    }
+
+    #[test]
+    fn forwarded_host_obfuscated() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "host=unknown"))
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "rust-lang.org");
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, r#"host="_hidden""#))
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "rust-lang.org");
+    }
+
+    #[test]
+    fn forwarded_case_sensitivity() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "For=192.0.2.60"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+    }
+
+    #[test]
+    fn forwarded_weird_whitespace() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for= 1.2.3.4; proto= https"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("1.2.3.4"));
+        assert_eq!(info.scheme(), "https");
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "  for = 1.2.3.4  "))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("1.2.3.4"));
+    }
+
+    #[test]
+    fn forwarded_for_quoted() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, r#"for="192.0.2.60:8080""#))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60:8080"));
+    }
+
+    #[test]
+    fn forwarded_quoted_equals() {
+        let req = TestRequest::default()
+            .insert_header((
+                header::FORWARDED,
+                r#"host="a=b.example.com"; proto=https; for="_a=b""#,
+            ))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.host(), "a=b.example.com");
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.realip_remote_addr(), Some("_a=b"));
+    }
+
+    #[test]
+    fn forwarded_for_ipv6() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, r#"for="[2001:db8:cafe::17]:4711""#))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("[2001:db8:cafe::17]:4711"));
+    }
+
+    #[test]
+    fn forwarded_for_multiple() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=192.0.2.60, for=198.51.100.17"))
+            .to_http_request();
+        let info = req.connection_info();
+        // takes the first value
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+    }
+
+    #[test]
+    fn forwarded_adversarial_input() {
+        use rand::{rngs::StdRng, Rng as _, SeedableRng as _};
+
+        use crate::http::header::HeaderValue;
+
+        const ALPHABET: &[u8] = b"=;,\"\\ \tforprotohostbyFORunknown_:[]0123456789.";
+
+        // fixed seed keeps failures reproducible
+        let mut rng = StdRng::seed_from_u64(7239);
+
+        for _ in 0..2_000 {
+            let len = rng.gen_range(0..512);
+            let bytes = (0..len)
+                .map(|_| {
+                    if rng.gen_bool(0.9) {
+                        ALPHABET[rng.gen_range(0..ALPHABET.len())]
+                    } else {
+                        rng.gen()
+                    }
+                })
+                .collect::<Vec<u8>>();
+
+            let val = match HeaderValue::from_bytes(&bytes) {
+                Ok(val) => val,
+                Err(_) => continue,
+            };
+
+            let req = TestRequest::default()
+                .insert_header((header::FORWARDED, val))
+                .to_http_request();
+            let info = req.connection_info();
+
+            // resolved values are always sub-slices of the input or fixed defaults
+            let bound = len.max("localhost:8080".len());
+            assert!(info.scheme().len() <= bound);
+            assert!(info.host().len() <= bound);
+            assert!(info.realip_remote_addr().map_or(0, str::len) <= bound);
+            assert!(info.proxy_path().len() <= MAX_RETAINED_HOPS);
+            assert!(info.lazy.forwarded_elements().len() <= MAX_RETAINED_HOPS);
+        }
+
+        // deep chains, a single huge element and runs of delimiters
+        let deep = (0..10_000)
+            .map(|idx| format!("for=10.0.{}.{};by=_p{}", idx / 256 % 256, idx % 256, idx))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let wide = format!("for=192.0.2.60;{}", "x=y;".repeat(20_000));
+        let delims = [";".repeat(50_000), "=".repeat(50_000), ";=".repeat(25_000)];
+
+        for val in [&deep, &wide].iter().copied().chain(&delims) {
+            let req = TestRequest::default()
+                .insert_header((header::FORWARDED, val.as_str()))
+                .insert_header((X_FORWARDED_FOR, val.as_str()))
+                .to_http_request();
+            let info = req.connection_info();
+
+            assert!(info.forwarded_for.len() <= MAX_RETAINED_HOPS);
+            assert!(info.proxy_path().len() <= MAX_RETAINED_HOPS);
+            assert!(info.lazy.forwarded_elements().len() <= MAX_RETAINED_HOPS);
+            assert!(info.host().len() <= "localhost:8080".len());
+        }
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, deep))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.hop_count(), 10_000);
+        assert!(info.validate_hop_count(MAX_RETAINED_HOPS).is_err());
+        assert_eq!(info.forwarded_for.len(), MAX_RETAINED_HOPS);
+        assert_eq!(info.lazy.forwarded_elements().len(), MAX_RETAINED_HOPS);
+
+        // the hops nearest to this server are the ones kept
+        let path = info.proxy_path();
+        assert_eq!(path.len(), MAX_RETAINED_HOPS);
+        assert_eq!(
+            path.last().unwrap(),
+            &(Some("_p9999".to_owned()), Some("10.0.39.15".to_owned()))
+        );
+
+        let xff = vec!["198.51.100.17"; 10_000].join(", ");
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, xff))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.hop_count(), 10_000);
+        assert_eq!(
+            info.next_x_forwarded_for("10.0.0.1".parse().unwrap())
+                .split(", ")
+                .count(),
+            MAX_RETAINED_HOPS + 1
+        );
+    }
+
+    #[test]
+    fn forwarded_oversized_input() {
+        let huge = "for=1.2.3.4, ".repeat(8_192);
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, huge))
+            .to_http_request();
+        assert_eq!(req.connection_info().realip_remote_addr(), Some("1.2.3.4"));
+
+        let quotes = format!("host={}a{}", "\"".repeat(4_096), "\"".repeat(4_096));
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, quotes))
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "a");
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "=;=,==;;,,\"\"\"="))
+            .to_http_request();
+        assert_eq!(req.connection_info().scheme(), "http");
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn resolution_span_fields() {
+        use std::{
+            collections::HashMap,
+            fmt,
+            sync::{Arc, Mutex},
+        };
+
+        use tracing::{
+            field::{Field, Visit},
+            span, Event, Metadata, Subscriber,
+        };
+
+        #[derive(Clone, Default)]
+        struct Capture(Arc<Mutex<HashMap<&'static str, String>>>);
+
+        impl Visit for Capture {
+            fn record_str(&mut self, field: &Field, value: &str) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .insert(field.name(), value.to_owned());
+            }
+
+            fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .insert(field.name(), format!("{:?}", value));
+            }
+        }
+
+        impl Subscriber for Capture {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+                span.record(&mut self.clone());
+                span::Id::from_u64(1)
+            }
+
+            fn record(&self, _: &span::Id, values: &span::Record<'_>) {
+                values.record(&mut self.clone());
+            }
+
+            fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+            fn event(&self, _: &Event<'_>) {}
+            fn enter(&self, _: &span::Id) {}
+            fn exit(&self, _: &span::Id) {}
+        }
+
+        let capture = Capture::default();
+
+        tracing::subscriber::with_default(capture.clone(), || {
+            let req = TestRequest::default()
+                .insert_header((header::FORWARDED, "for=192.0.2.60"))
+                .insert_header((X_FORWARDED_PROTO, "https"))
+                .insert_header((header::HOST, "rust-lang.org"))
+                .to_http_request();
+            let _ = req.connection_info();
+        });
+
+        let fields = capture.0.lock().unwrap();
+        assert_eq!(fields["forwarded.for"], "192.0.2.60");
+        assert_eq!(fields["x_forwarded_proto"], "https");
+        assert_eq!(fields["host_header"], "rust-lang.org");
+        assert_eq!(fields["app_config.host"], "localhost:8080");
+        assert_eq!(fields["scheme"], "https");
+        assert_eq!(fields["host"], "rust-lang.org");
+        assert_eq!(fields["realip"], "192.0.2.60");
+        assert!(!fields.contains_key("forwarded.proto"));
+        assert!(!fields.contains_key("x_forwarded_host"));
+    }
+
+    #[test]
+    fn via_header() {
+        let req = TestRequest::default().to_http_request();
+        assert!(req.connection_info().via_chain().is_empty());
+
+        let req = TestRequest::default()
+            .insert_header((header::VIA, "1.1 vegur, 1.0 fred"))
+            .to_http_request();
+        let info = req.connection_info();
+        let chain = info.via_chain();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[0].protocol, "1.1");
+        assert_eq!(chain[0].received_by, "vegur");
+        assert_eq!(chain[1].protocol, "1.0");
+        assert_eq!(chain[1].received_by, "fred");
+
+        let req = TestRequest::default()
+            .append_header((header::VIA, "HTTP/1.1 proxy.example:8080 (Apache/1.1)"))
+            .append_header((header::VIA, "2 edge, bogus"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(
+            info.via_chain(),
+            &[
+                ViaHop {
+                    protocol: "HTTP/1.1".to_owned(),
+                    received_by: "proxy.example:8080".to_owned(),
+                },
+                ViaHop {
+                    protocol: "2".to_owned(),
+                    received_by: "edge".to_owned(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn scheme_source() {
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.connection_info().scheme_source(), SchemeSource::Default);
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "proto=https"))
+            .insert_header((X_FORWARDED_PROTO, "http"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().scheme_source(),
+            SchemeSource::Forwarded
+        );
+
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().scheme_source(),
+            SchemeSource::XForwardedProto
+        );
+
+        let req = TestRequest::with_uri("https://actix.rs/").to_http_request();
+        assert_eq!(req.connection_info().scheme_source(), SchemeSource::Uri);
+
+        let cfg = AppConfig::new(
+            true,
+            "localhost:8443".to_owned(),
+            "127.0.0.1:8443".parse().unwrap(),
+        );
+        let req = TestRequest::default().to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg, &DEFAULT_CONFIG);
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.scheme_source(), SchemeSource::Tls);
+    }
+
+    #[test]
+    fn secure_cookies() {
+        let tls_cfg = AppConfig::new(
+            true,
+            "localhost:8443".to_owned(),
+            "127.0.0.1:8443".parse().unwrap(),
+        );
+
+        // trustworthy https
+        let req = TestRequest::default().to_http_request();
+        let info = ConnectionInfo::new(req.head(), &tls_cfg, &DEFAULT_CONFIG);
+        assert!(info.should_set_secure_cookies(req.head()));
+
+        // proxy claims https over TLS connection; fine since the transport is secure
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &tls_cfg, &DEFAULT_CONFIG);
+        assert!(info.should_set_secure_cookies(req.head()));
+
+        // TLS terminated by an acceptor recording connection data on a plaintext config
+        let req = TestRequest::default().tls(true).to_http_request();
+        assert!(req.connection_info().should_set_secure_cookies(req.head()));
+
+        let info_cfg = ConnectionInfoConfig::default()
+            .trusted_proxies(TrustedProxies::new().network("10.0.0.0/8".parse().unwrap()));
+
+        // https reported by a trusted TLS-terminating proxy
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:50000".parse().unwrap())
+            .insert_header((header::FORWARDED, "proto=https"))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        assert_eq!(req.connection_info().scheme(), "https");
+        assert!(req.connection_info().should_set_secure_cookies(req.head()));
+
+        // forwarding headers are honored from anyone without trusted proxies
+        let req = TestRequest::default()
+            .peer_addr("203.0.113.5:50000".parse().unwrap())
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .to_http_request();
+        assert_eq!(req.connection_info().scheme(), "https");
+        assert!(!req.connection_info().should_set_secure_cookies(req.head()));
+
+        // spoofed https from an untrusted peer
+        let req = TestRequest::default()
+            .peer_addr("203.0.113.5:50000".parse().unwrap())
+            .insert_header((header::FORWARDED, "proto=https"))
+            .app_data(info_cfg)
+            .to_http_request();
+        assert_eq!(req.connection_info().scheme(), "http");
+        assert!(!req.connection_info().should_set_secure_cookies(req.head()));
+
+        let req = TestRequest::with_uri("https://actix.rs/").to_http_request();
+        assert!(!req.connection_info().should_set_secure_cookies(req.head()));
+
+        // plaintext
+        let req = TestRequest::default().to_http_request();
+        assert!(!req.connection_info().should_set_secure_cookies(req.head()));
+
+        // TLS connection downgraded by proxy header
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "http"))
+            .to_http_request();
+        let info = ConnectionInfo::new(req.head(), &tls_cfg, &DEFAULT_CONFIG);
+        assert!(!info.should_set_secure_cookies(req.head()));
+    }
+
+    #[test]
+    fn realip_resolver() {
+        let info_cfg = ConnectionInfoConfig::default()
+            .trusted_proxies(TrustedProxies::new())
+            .realip_resolver(Box::new(|req| {
+                if req.headers.get("x-edge-secret")? != "s3cr3t" {
+                    return None;
+                }
+
+                req.headers
+                    .get(X_FORWARDED_FOR)?
+                    .to_str()
+                    .ok()?
+                    .parse()
+                    .ok()
+            }));
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .insert_header(("x-edge-secret", "s3cr3t"))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+        assert!(info.explain().contains("(from custom resolver)"));
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .insert_header(("x-edge-secret", "guess"))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .app_data(info_cfg)
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("127.0.0.1:8080")
+        );
+    }
+
+    #[test]
+    fn connection_info_resolver() {
+        struct Fixed;
+
+        impl ConnectionInfoResolver for Fixed {
+            fn resolve(&self, _: &RequestHead, _: &AppConfig) -> ConnectionInfo {
+                ConnectionInfo {
+                    scheme: "https".to_owned(),
+                    host: "fixed.example".to_owned(),
+                    realip_remote_addr: Some("192.0.2.1".to_owned()),
+                    realip_source: RealIpSource::Resolver,
+                    ..ConnectionInfo::default()
+                }
+            }
+        }
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .insert_header((header::HOST, "rust-lang.org"))
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17"))
+            .app_data(ConnectionInfoConfig::default().connection_info_resolver(Fixed))
+            .to_http_request();
+
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "fixed.example");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.1"));
+        drop(info);
+        assert_eq!(
+            req.extensions().get::<ResolvedClientIp>().map(|ip| ip.0),
+            Some("192.0.2.1".parse().unwrap())
+        );
+
+        // the default resolver honours its own config
+        let resolver = DefaultResolver::new(
+            ConnectionInfoConfig::default().realip_strategy(RealIpStrategy::LastHop),
+        );
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60, 198.51.100.17"))
+            .app_data(ConnectionInfoConfig::default().connection_info_resolver(resolver))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("198.51.100.17")
+        );
+    }
+
+    #[test]
+    fn realip_strategy() {
+        let first_hop = ConnectionInfoConfig::default();
+        let last_hop = ConnectionInfoConfig::default().realip_strategy(RealIpStrategy::LastHop);
+
+        let req = || {
+            TestRequest::default()
+                .insert_header((X_FORWARDED_FOR, "192.0.2.60, 198.51.100.17, 203.0.113.43"))
+                .to_http_request()
+        };
+
+        let req1 = req();
+        let info = ConnectionInfo::new(req1.head(), req1.app_config(), &first_hop);
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+
+        let req1 = req();
+        let info = ConnectionInfo::new(req1.head(), req1.app_config(), &last_hop);
+        assert_eq!(info.realip_remote_addr(), Some("203.0.113.43"));
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=192.0.2.60, for=198.51.100.17"))
+            .app_data(last_hop)
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("198.51.100.17")
+        );
+
+        // entries across multiple headers form one chain
+        let req = TestRequest::default()
+            .append_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .append_header((X_FORWARDED_FOR, "198.51.100.17"))
+            .app_data(ConnectionInfoConfig::default().realip_strategy(RealIpStrategy::LastHop))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("198.51.100.17")
+        );
+    }
+
+    #[test]
+    fn x_forwarded_scheme_header() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_SCHEME, "https"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.scheme_source(), SchemeSource::XForwardedScheme);
+
+        // X-Forwarded-Proto is preferred
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "http"))
+            .insert_header((X_FORWARDED_SCHEME, "https"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.scheme_source(), SchemeSource::XForwardedProto);
+    }
+
+    #[test]
+    fn x_forwarded_ssl_header() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_SSL, "On"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.scheme_source(), SchemeSource::XForwardedSsl);
+
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_SSL, "off"))
+            .to_http_request();
+        assert_eq!(req.connection_info().scheme(), "http");
+
+        // X-Forwarded-Scheme is preferred
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_SCHEME, "http"))
+            .insert_header((X_FORWARDED_SSL, "on"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.scheme_source(), SchemeSource::XForwardedScheme);
+    }
+
+    #[test]
+    fn default_scheme() {
+        let req = TestRequest::default()
+            .app_data(ConnectionInfoConfig::default().default_scheme("https"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.scheme_source(), SchemeSource::Default);
+
+        // headers still take precedence
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "http"))
+            .app_data(ConnectionInfoConfig::default().default_scheme("https"))
+            .to_http_request();
+        assert_eq!(req.connection_info().scheme(), "http");
+    }
+
+    #[test]
+    fn scheme_from_uri() {
+        let req = TestRequest::get()
+            .uri("https://actix.rs/test")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+    }
+
+    #[test]
+    fn host_header_utf8() {
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "bücher.example"))
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "bücher.example");
+    }
+
+    #[test]
+    fn host_from_uri() {
+        let req = TestRequest::get()
+            .uri("https://actix.rs/test")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.host(), "actix.rs");
+    }
+
+    #[test]
+    fn host_from_absolute_form_uri() {
+        let req = TestRequest::get()
+            .uri("http://rust-lang.org/learn")
+            .to_http_request();
+        assert!(req.headers().get(header::HOST).is_none());
+        let info = req.connection_info();
+        assert_eq!(info.host(), "rust-lang.org");
+        assert!(info.explain().contains("(from request URI)"));
+
+        // replaces Host header in HTTP/1
+        let req = TestRequest::get()
+            .uri("http://rust-lang.org/learn")
+            .insert_header((header::HOST, "evil.example"))
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "rust-lang.org");
+
+        let req = TestRequest::get()
+            .version(Version::HTTP_2)
+            .uri("http://rust-lang.org/learn")
+            .insert_header((header::HOST, "actix.rs"))
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "actix.rs");
+    }
+
+    #[test]
+    fn prefer_http2_authority() {
+        let req = TestRequest::get()
+            .version(Version::HTTP_2)
+            .uri("https://rust-lang.org/learn")
+            .insert_header((header::HOST, "evil.example"))
+            .app_data(ConnectionInfoConfig::default().prefer_http2_authority(true))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.host(), "rust-lang.org");
+        assert!(info.explain().contains("(from request URI)"));
+
+        // forwarding headers still win
+        let req = TestRequest::get()
+            .version(Version::HTTP_2)
+            .uri("https://rust-lang.org/learn")
+            .insert_header((X_FORWARDED_HOST, "actix.rs"))
+            .app_data(ConnectionInfoConfig::default().prefer_http2_authority(true))
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "actix.rs");
+    }
+
+    #[test]
+    fn sni_host_fallback() {
+        let cfg = || ConnectionInfoConfig::default().sni_host_fallback(true);
+
+        let req = TestRequest::default()
+            .version(Version::HTTP_2)
+            .sni("rust-lang.org")
+            .app_data(cfg())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.host(), "rust-lang.org");
+        assert!(info.explain().contains("(from TLS SNI)"));
+
+        // any request host wins
+        let req = TestRequest::default()
+            .sni("rust-lang.org")
+            .insert_header((header::HOST, "actix.rs"))
+            .app_data(cfg())
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "actix.rs");
+
+        let req = TestRequest::default()
+            .sni("rust-lang.org")
+            .insert_header((X_FORWARDED_HOST, "actix.rs"))
+            .app_data(cfg())
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "actix.rs");
+
+        // disabled by default
+        let req = TestRequest::default()
+            .sni("rust-lang.org")
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "localhost:8080");
+    }
+
+    #[test]
+    fn host_header_port_wins_over_authority_port() {
+        let req = TestRequest::get()
+            .version(Version::HTTP_2)
+            .uri("https://example.com:443/")
+            .insert_header((header::HOST, "example.com:8080"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.host(), "example.com:8080");
+        assert!(info.explain().contains("(from Host header)"));
+        drop(info);
+
+        // without a Host header the authority, including its port, is used
+        let req = TestRequest::get()
+            .version(Version::HTTP_2)
+            .uri("https://example.com:443/")
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "example.com:443");
+
+        // HTTP/1 absolute-form targets replace the Host header entirely
+        let req = TestRequest::get()
+            .uri("http://example.com:443/")
+            .insert_header((header::HOST, "example.com:8080"))
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "example.com:443");
+    }
+
+    #[test]
+    fn host_from_server_hostname() {
+        let mut req = TestRequest::get();
+        req.set_server_hostname("actix.rs");
+        let req = req.to_http_request();
+
+        let info = req.connection_info();
+        assert_eq!(info.host(), "actix.rs");
+    }
+
+    #[actix_rt::test]
+    async fn conn_info_extract() {
+        let req = TestRequest::default()
+            .uri("https://actix.rs/test")
+            .to_http_request();
+        let conn_info = ConnectionInfo::extract(&req).await.unwrap();
+        assert_eq!(conn_info.scheme(), "https");
+        assert_eq!(conn_info.host(), "actix.rs");
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    fn host_idna_ascii() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_HOST, "bücher.example"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().host_idna_ascii().unwrap(),
+            "xn--bcher-kva.example"
+        );
+
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_HOST, "Bücher.example:8080"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().host_idna_ascii().unwrap(),
+            "xn--bcher-kva.example:8080"
+        );
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "xn--bcher-kva.example"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().host_idna_ascii().unwrap(),
+            "xn--bcher-kva.example"
+        );
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().host_idna_ascii().unwrap(),
+            "rust-lang.org"
+        );
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "[::1]:8080"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().host_idna_ascii().unwrap(),
+            "[::1]:8080"
+        );
+    }
+
+    #[test]
+    fn base_url() {
+        let info = |scheme: &str, host: &str| ConnectionInfo {
+            scheme: scheme.to_owned(),
+            host: host.to_owned(),
+            ..ConnectionInfo::default()
+        };
+
+        let url = info("http", "example.com").base_url().unwrap();
+        assert_eq!(url.as_str(), "http://example.com/");
+
+        let url = info("https", "example.com").base_url().unwrap();
+        assert_eq!(url.as_str(), "https://example.com/");
+
+        let url = info("http", "example.com:8080").base_url().unwrap();
+        assert_eq!(url.as_str(), "http://example.com:8080/");
+        assert_eq!(url.port(), Some(8080));
+
+        let url = info("https", "example.com:443").base_url().unwrap();
+        assert_eq!(url.as_str(), "https://example.com/");
+
+        let url = info("https", "[::1]:8443").base_url().unwrap();
+        assert_eq!(url.as_str(), "https://[::1]:8443/");
+
+        assert!(info("https", "exa mple.com").base_url().is_err());
+    }
+
+    #[test]
+    fn borrowed_fields() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=192.0.2.60; proto=https"))
+            .insert_header((header::HOST, "rust-lang.org"))
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .to_http_request();
+        let info = req.connection_info();
+
+        let fields = info.fields();
+        assert_eq!(fields.scheme, info.scheme());
+        assert_eq!(fields.host, info.host());
+        assert_eq!(fields.realip, info.realip_remote_addr());
+        assert_eq!(fields.peer, info.remote_addr());
+
+        // borrows rather than copies
+        assert!(std::ptr::eq(fields.host, info.host()));
+
+        assert_eq!(
+            serde_json::to_string(&fields).unwrap(),
+            r#"{"scheme":"https","host":"rust-lang.org","realip":"192.0.2.60","peer":"127.0.0.1:8080"}"#
+        );
+    }
+
+    #[test]
+    fn origin_hash() {
+        let info = |scheme: &str, host: &str| ConnectionInfo {
+            scheme: scheme.to_owned(),
+            host: host.to_owned(),
+            ..ConnectionInfo::default()
+        };
+
+        let hash = info("https", "rust-lang.org").origin_hash();
+
+        // fixed value demonstrates stability across runs
+        assert_eq!(hash, 0x9d81_61ec_921d_1543);
+        assert_eq!(info("HTTPS", "Rust-Lang.org").origin_hash(), hash);
+        assert_eq!(info("https", "rust-lang.org:443").origin_hash(), hash);
+        assert_ne!(info("https", "rust-lang.org:8443").origin_hash(), hash);
+        assert_ne!(info("http", "rust-lang.org").origin_hash(), hash);
+        assert_ne!(info("https", "actix.rs").origin_hash(), hash);
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "proto=https; host=rust-lang.org"))
+            .to_http_request();
+        assert_eq!(req.connection_info().origin_hash(), hash);
+    }
+
+    #[test]
+    fn is_same_origin() {
+        let req = |name, value| {
+            TestRequest::default()
+                .insert_header((header::HOST, "rust-lang.org"))
+                .insert_header((name, value))
+                .to_http_request()
+        };
+
+        let req1 = req(header::ORIGIN, "http://rust-lang.org");
+        assert!(req1.connection_info().is_same_origin(req1.head()));
+
+        let req1 = req(header::ORIGIN, "HTTP://Rust-Lang.org:80");
+        assert!(req1.connection_info().is_same_origin(req1.head()));
+
+        let req1 = req(header::REFERER, "http://rust-lang.org/learn?q=1");
+        assert!(req1.connection_info().is_same_origin(req1.head()));
+
+        let req1 = req(header::ORIGIN, "https://rust-lang.org");
+        assert!(!req1.connection_info().is_same_origin(req1.head()));
+
+        let req1 = req(header::ORIGIN, "http://rust-lang.org:8080");
+        assert!(!req1.connection_info().is_same_origin(req1.head()));
+
+        let req1 = req(header::ORIGIN, "http://evil.example");
+        assert!(!req1.connection_info().is_same_origin(req1.head()));
+
+        let req1 = req(header::ORIGIN, "null");
+        assert!(!req1.connection_info().is_same_origin(req1.head()));
+
+        // origin takes precedence over referer
+        let req1 = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .insert_header((header::ORIGIN, "null"))
+            .insert_header((header::REFERER, "http://rust-lang.org/"))
+            .to_http_request();
+        assert!(!req1.connection_info().is_same_origin(req1.head()));
+
+        let req1 = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+        assert!(!req1.connection_info().is_same_origin(req1.head()));
+    }
+
+    #[cfg(feature = "registrable-domain")]
+    #[test]
+    fn registrable_domain() {
+        struct MockList;
+
+        impl PublicSuffixList for MockList {
+            fn public_suffix<'a>(&self, domain: &'a str) -> Option<&'a str> {
+                ["co.uk", "uk", "com"]
+                    .iter()
+                    .find(|suffix| {
+                        domain == **suffix || domain.ends_with(&format!(".{}", suffix))
+                    })
+                    .map(|suffix| &domain[domain.len() - suffix.len()..])
+            }
+        }
+
+        let info_cfg = ConnectionInfoConfig::default().public_suffix_list(MockList);
+
+        for (host, domain) in &[
+            ("a.b.example.co.uk", Some("example.co.uk")),
+            ("Example.CO.UK:8443", Some("example.co.uk")),
+            ("rust-lang.com.", Some("rust-lang.com")),
+            ("co.uk", None),
+            ("localhost", None),
+            ("192.0.2.60:8080", None),
+            ("[2001:db8::1]:8080", None),
+        ] {
+            let req = TestRequest::default()
+                .insert_header((header::HOST, *host))
+                .app_data(info_cfg.clone())
+                .to_http_request();
+            assert_eq!(
+                req.connection_info().registrable_domain().as_deref(),
+                *domain,
+                "{}",
+                host
+            );
+        }
+
+        // no list configured
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "a.b.example.co.uk"))
+            .to_http_request();
+        assert_eq!(req.connection_info().registrable_domain(), None);
+
+        // untrusted peers cannot pick the cookie domain through forwarding headers
+        let info_cfg =
+            info_cfg.trusted_proxies(TrustedProxies::new().ip("10.0.0.1".parse().unwrap()));
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .insert_header((header::HOST, "www.example.co.uk"))
+            .insert_header((X_FORWARDED_HOST, "attacker.co.uk"))
+            .app_data(info_cfg)
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().registrable_domain().as_deref(),
+            Some("example.co.uk")
+        );
+    }
+
+    #[cfg(feature = "reverse-dns")]
+    #[actix_rt::test]
+    async fn resolve_peer_hostname() {
+        struct MockResolver;
+
+        impl PeerHostnameResolver for MockResolver {
+            fn resolve(&self, ip: IpAddr) -> LocalBoxFuture<'static, Option<String>> {
+                let name = match ip {
+                    IpAddr::V4(ip) if ip == std::net::Ipv4Addr::new(192, 0, 2, 60) => {
+                        Some("client.example.com".to_owned())
+                    }
+                    _ => None,
+                };
+
+                Box::pin(async move { name })
+            }
+        }
+
+        let info_cfg = ConnectionInfoConfig::default().peer_hostname_resolver(MockResolver);
+
+        // don't hold the extensions borrow across await points
+        let info = |req: &HttpRequest| req.connection_info().clone();
+
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        assert_eq!(
+            info(&req).resolve_peer_hostname().await.unwrap(),
+            "client.example.com"
+        );
+
+        let req = TestRequest::default()
+            .peer_addr("198.51.100.17:8080".parse().unwrap())
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        assert!(info(&req).resolve_peer_hostname().await.is_none());
+
+        // no peer address to look up
+        let req = TestRequest::default().app_data(info_cfg).to_http_request();
+        assert!(info(&req).resolve_peer_hostname().await.is_none());
+
+        // no resolver configured
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .to_http_request();
+        assert!(info(&req).resolve_peer_hostname().await.is_none());
+    }
+
+    #[test]
+    fn get_does_not_bypass_app_config() {
+        struct Fixed;
+
+        impl ConnectionInfoResolver for Fixed {
+            fn resolve(&self, _: &RequestHead, _: &AppConfig) -> ConnectionInfo {
+                ConnectionInfo {
+                    host: "fixed.example".to_owned(),
+                    ..ConnectionInfo::default()
+                }
+            }
+        }
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .app_data(ConnectionInfoConfig::default().connection_info_resolver(Fixed))
+            .to_http_request();
+
+        // eg. middleware that only has the head resolves with the default config first
+        assert_eq!(
+            ConnectionInfo::get(req.head(), req.app_config()).host(),
+            "rust-lang.org"
+        );
+
+        // the resolver still applies, and is then returned by `get` too
+        assert_eq!(req.connection_info().host(), "fixed.example");
+        assert_eq!(
+            ConnectionInfo::get(req.head(), req.app_config()).host(),
+            "fixed.example"
+        );
+    }
+
+    #[test]
+    fn connection_info_while_get_is_borrowed() {
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .insert_header((X_FORWARDED_HOST, "forwarded.example"))
+            .app_data(ConnectionInfoConfig::default().require_tls_for_forwarding(true))
+            .to_http_request();
+
+        let default_info = ConnectionInfo::get(req.head(), req.app_config());
+        assert_eq!(default_info.host(), "forwarded.example");
+
+        // resolves with the app config without inserting into the borrowed extensions
+        assert_eq!(req.connection_info().host(), "rust-lang.org");
+        assert_eq!(req.connection_info().host(), "rust-lang.org");
+        drop(default_info);
+
+        assert_eq!(req.connection_info().host(), "rust-lang.org");
+        assert_eq!(
+            ConnectionInfo::get(req.head(), req.app_config()).host(),
+            "rust-lang.org"
+        );
+    }
+
+    #[test]
+    fn get_is_cached() {
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+
+        let before = PARSE_COUNT.with(Cell::get);
+
+        for _ in 0..3 {
+            assert_eq!(req.connection_info().host(), "rust-lang.org");
+            assert_eq!(
+                ConnectionInfo::get(req.head(), req.app_config()).host(),
+                "rust-lang.org"
+            );
+        }
+
+        assert_eq!(PARSE_COUNT.with(Cell::get), before + 1);
+    }
+
+    #[test]
+    fn explain() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .insert_header((header::HOST, "example.com"))
+            .insert_header((header::FORWARDED, "for=1.2.3.4"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().explain(),
+            "scheme=https (from X-Forwarded-Proto)\n\
+             host=example.com (from Host header)\n\
+             realip=1.2.3.4 (from Forwarded)"
+        );
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "proto=https; host=rust-lang.org"))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().explain(),
+            "scheme=https (from Forwarded)\n\
+             host=rust-lang.org (from Forwarded)\n\
+             realip=192.0.2.60 (from X-Forwarded-For)"
+        );
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().explain(),
+            "scheme=http (from default)\n\
+             host=localhost:8080 (from app config)\n\
+             realip=127.0.0.1:8080 (from peer address)"
+        );
+
+        let req = TestRequest::default().to_http_request();
+        assert!(req
+            .connection_info()
+            .explain()
+            .ends_with("realip=- (unavailable)"));
+    }
+
+    #[test]
+    fn trusted_proxies_compare_addresses() {
+        let proxies = TrustedProxies::new().ip("::1".parse().unwrap());
+        assert!(proxies.contains("0:0:0:0:0:0:0:1".parse().unwrap()));
+        assert!(proxies.contains("0000:0000::0001".parse().unwrap()));
+        assert!(!proxies.contains("::2".parse().unwrap()));
+
+        let proxies = TrustedProxies::new().ip("2001:db8:0:0:0:0:0:1".parse().unwrap());
+        assert!(proxies.contains("2001:DB8::1".parse().unwrap()));
+
+        let proxies = TrustedProxies::new().ip("::ffff:10.0.0.1".parse().unwrap());
+        assert!(proxies.contains("10.0.0.1".parse().unwrap()));
+
+        let proxies = TrustedProxies::new().network("10.0.0.0/8".parse().unwrap());
+        assert!(proxies.contains("::ffff:10.1.2.3".parse().unwrap()));
+    }
+
+    #[test]
+    fn trusted_proxies_scheme_and_host() {
+        let info_cfg = ConnectionInfoConfig::default()
+            .trusted_proxies(TrustedProxies::new().ip("10.0.0.1".parse().unwrap()));
+
+        let req = |peer: &str| {
+            TestRequest::default()
+                .peer_addr(peer.parse().unwrap())
+                .insert_header((header::HOST, "origin.internal"))
+                .insert_header((header::FORWARDED, "proto=https;host=spoofed.example"))
+                .insert_header((X_FORWARDED_PROTO, "https"))
+                .insert_header((X_FORWARDED_HOST, "spoofed.example"))
+                .app_data(info_cfg.clone())
+                .to_http_request()
+        };
+
+        // untrusted peers cannot set the scheme or host
+        let req1 = req("192.0.2.60:8080");
+        let info = req1.connection_info();
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.scheme_source(), SchemeSource::Default);
+        assert_eq!(info.host(), "origin.internal");
+        assert!(!info.is_secure());
+
+        let req2 = req("10.0.0.1:8080");
+        let info = req2.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "spoofed.example");
+    }
+
+    #[test]
+    fn trusted_proxies_unterminated_quote() {
+        let info_cfg = ConnectionInfoConfig::default()
+            .trusted_proxies(TrustedProxies::new().ip("10.0.0.1".parse().unwrap()));
+
+        // client sent a stray quote, the trusted proxy appended the client's address
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .insert_header((header::FORWARDED, r#"for="1.2.3.4, for=198.51.100.17"#))
+            .app_data(info_cfg)
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("198.51.100.17")
+        );
+    }
+
+    #[test]
+    fn trusted_proxies_realip() {
+        let info_cfg = ConnectionInfoConfig::default().trusted_proxies(
+            TrustedProxies::new()
+                .ip("0:0:0:0:0:0:0:1".parse().unwrap())
+                .ip("2001:db8::10".parse().unwrap()),
+        );
+
+        // peer written differently than the configured address is still trusted
+        let req = TestRequest::default()
+            .peer_addr("[::1]:8080".parse().unwrap())
+            .insert_header((
+                header::FORWARDED,
+                r#"for=192.0.2.60, for="[2001:0db8:0000:0000:0000:0000:0000:0010]""#,
+            ))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("192.0.2.60")
+        );
+
+        // untrusted hop in the chain is the client
+        let req = TestRequest::default()
+            .peer_addr("[::1]:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60, 198.51.100.17, 2001:db8::10"))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("198.51.100.17")
+        );
+
+        // untrusted peer; headers ignored
+        let req = TestRequest::default()
+            .peer_addr("[::2]:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .app_data(info_cfg)
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("[::2]:8080")
+        );
+    }
+
+    #[test]
+    fn trust_loopback() {
+        let realip = |peer: &str, info_cfg: &ConnectionInfoConfig| {
+            let req = TestRequest::default()
+                .peer_addr(peer.parse().unwrap())
+                .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+                .app_data(info_cfg.clone())
+                .to_http_request();
+            let info = req.connection_info();
+            (
+                info.realip_remote_addr().unwrap().to_owned(),
+                info.trusted_proxy_matched().copied(),
+            )
+        };
+
+        // loopback peers are trusted by default
+        let info_cfg = ConnectionInfoConfig::default()
+            .trusted_proxies(TrustedProxies::new().network("10.0.0.0/8".parse().unwrap()));
+        for peer in &[
+            "127.0.0.1:8080",
+            "127.0.1.1:8080",
+            "[::1]:8080",
+            "[::ffff:127.0.0.1]:8080",
+        ] {
+            let (ip, net) = realip(peer, &info_cfg);
+            assert_eq!(ip, "192.0.2.60", "{}", peer);
+            assert!(net.is_some(), "{}", peer);
+        }
+        assert_eq!(realip("10.0.0.1:8080", &info_cfg).0, "192.0.2.60");
+
+        // public peers are not trusted unless configured
+        assert_eq!(realip("203.0.113.9:8080", &info_cfg).0, "203.0.113.9:8080");
+        let with_public = info_cfg
+            .clone()
+            .trusted_proxies("10.0.0.0/8, 203.0.113.9".parse().unwrap());
+        assert_eq!(realip("203.0.113.9:8080", &with_public).0, "192.0.2.60");
+
+        // disabled, in either builder order
+        for info_cfg in &[
+            info_cfg.clone().trust_loopback(false),
+            ConnectionInfoConfig::default()
+                .trust_loopback(false)
+                .trusted_proxies(TrustedProxies::new().network("10.0.0.0/8".parse().unwrap())),
+        ] {
+            let (ip, net) = realip("127.0.0.1:8080", info_cfg);
+            assert_eq!(ip, "127.0.0.1:8080");
+            assert_eq!(net, None);
+        }
+
+        // loopback is only added through config
+        assert!(!TrustedProxies::new().contains("127.0.0.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn redacted() {
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17"))
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+        let info = req.connection_info().redacted();
+        assert_eq!(info.realip_remote_addr(), Some("198.51.100.0"));
+        assert_eq!(info.remote_addr(), Some("192.0.2.0"));
+        assert_eq!(info.host(), "rust-lang.org");
+
+        let req = TestRequest::default()
+            .peer_addr("[2001:db8:85a3:1:2:8a2e:370:7334]:8080".parse().unwrap())
+            .insert_header((header::FORWARDED, r#"for="[2001:db8:abcd:12::1]""#))
+            .to_http_request();
+        let info = req.connection_info().redacted();
+        assert_eq!(info.realip_remote_addr(), Some("2001:db8:abcd::"));
+        assert_eq!(info.remote_addr(), Some("2001:db8:85a3::"));
+
+        // non-IP nodes are removed
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=_hidden"))
+            .to_http_request();
+        let info = req.connection_info().redacted();
+        assert_eq!(info.realip_remote_addr(), None);
+
+        // original is untouched
+        assert_eq!(req.connection_info().realip_remote_addr(), Some("_hidden"));
+
+        // multi-hop chains are masked too, so the debug output holds no full address
+        let req = TestRequest::default()
+            .peer_addr("203.0.113.43:8080".parse().unwrap())
+            .insert_header((
+                header::FORWARDED,
+                "for=192.0.2.60;by=198.51.100.1, for=198.51.100.17;by=_lb, for=_hidden",
+            ))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.61, 198.51.100.18"))
+            .to_http_request();
+        let info = req.connection_info().redacted();
+        assert_eq!(
+            info.forwarded_for,
+            &["192.0.2.0", "198.51.100.0", "_hidden"]
+        );
+        assert_eq!(
+            info.forwarded_without_last_hop().as_deref(),
+            Some("for=192.0.2.0;by=198.51.100.0, for=198.51.100.0;by=_lb")
+        );
+        let debug = format!("{:?}", info);
+        for full in &[
+            "192.0.2.60",
+            "192.0.2.61",
+            "198.51.100.1,",
+            "198.51.100.1\"",
+            "198.51.100.17",
+            "198.51.100.18",
+            "203.0.113.43",
+        ] {
+            assert!(!debug.contains(full), "{} in {}", full, debug);
+        }
+    }
+
+    #[test]
+    fn host_preference() {
+        let req = |info_cfg: ConnectionInfoConfig| {
+            TestRequest::default()
+                .insert_header((header::FORWARDED, "host=rust-lang.org"))
+                .insert_header((X_FORWARDED_HOST, "cdn.rust-lang.org"))
+                .app_data(info_cfg)
+                .to_http_request()
+        };
+
+        let req1 = req(ConnectionInfoConfig::default());
+        assert_eq!(req1.connection_info().host(), "rust-lang.org");
+
+        let req1 = req(
+            ConnectionInfoConfig::default().host_preference(HostPreference::XForwardedHost)
+        );
+        let info = req1.connection_info();
+        assert_eq!(info.host(), "cdn.rust-lang.org");
+        assert!(info.explain().contains("(from X-Forwarded-Host)"));
+
+        // falls back to Forwarded when preferred header is absent
+        let req1 = TestRequest::default()
+            .insert_header((header::FORWARDED, "host=rust-lang.org"))
+            .app_data(
+                ConnectionInfoConfig::default().host_preference(HostPreference::XForwardedHost),
+            )
+            .to_http_request();
+        assert_eq!(req1.connection_info().host(), "rust-lang.org");
+    }
+
+    #[actix_rt::test]
+    async fn single_parse_across_extractors() {
+        use actix_service::Service as _;
+
+        use crate::{
+            test::{call_service, init_service},
+            web, App, HttpResponse,
+        };
+
+        let srv = init_service(
+            App::new()
+                .wrap_fn(|req, srv| {
+                    assert_eq!(req.connection_info().host(), "rust-lang.org");
+                    srv.call(req)
+                })
+                .default_service(web::to(
+                    |req: HttpRequest, (info, peer): (ConnectionInfo, PeerAddr)| {
+                        assert_eq!(info.remote_addr(), Some(&*peer.0.to_string()));
+                        assert_eq!(req.connection_info().host(), "rust-lang.org");
+                        HttpResponse::Ok()
+                    },
+                )),
+        )
+        .await;
+
+        let before = PARSE_COUNT.with(Cell::get);
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        assert_eq!(PARSE_COUNT.with(Cell::get), before + 1);
+    }
+
+    #[actix_rt::test]
+    async fn replace_cached_from_middleware() {
+        use actix_service::Service as _;
+
+        use crate::{
+            test::{call_service, init_service, read_body},
+            web, App, HttpResponse,
+        };
+
+        let srv = init_service(
+            App::new()
+                .wrap_fn(|req, srv| {
+                    let mut info = req.connection_info().clone();
+                    info.set_host("rust-lang.org");
+                    info.set_scheme("https");
+                    info.replace_cached(req.head()).unwrap();
+                    srv.call(req)
+                })
+                .default_service(web::to(|req: HttpRequest, info: ConnectionInfo| {
+                    assert_eq!(info.scheme_source(), SchemeSource::Replaced);
+                    assert_eq!(req.connection_info().host(), "rust-lang.org");
+                    HttpResponse::Ok().body(format!(
+                        "{}://{} {}",
+                        info.scheme(),
+                        info.host(),
+                        info.realip_remote_addr().unwrap()
+                    ))
+                })),
+        )
+        .await;
+
+        let before = PARSE_COUNT.with(Cell::get);
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "www.rust-lang.org"))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(read_body(res).await, "https://rust-lang.org 192.0.2.60");
+
+        // the replaced info is not re-resolved
+        assert_eq!(PARSE_COUNT.with(Cell::get), before + 1);
+    }
+
+    #[test]
+    fn replace_cached_while_borrowed() {
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "www.rust-lang.org"))
+            .to_http_request();
+
+        let mut info = req.connection_info().clone();
+        info.set_host("rust-lang.org");
+
+        let cached = req.connection_info();
+        assert_eq!(
+            info.clone().replace_cached(req.head()),
+            Err(ConnectionInfoBorrowed)
+        );
+        assert_eq!(cached.host(), "www.rust-lang.org");
+        drop(cached);
+
+        assert_eq!(info.replace_cached(req.head()), Ok(()));
+        assert_eq!(req.connection_info().host(), "rust-lang.org");
+    }
+
+    #[test]
+    fn replace_cached_client_ip() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("192.0.2.60")
+        );
+
+        let other = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17:4711"))
+            .to_http_request();
+        let info = other.connection_info().clone();
+        info.replace_cached(req.head()).unwrap();
+        assert_eq!(
+            req.extensions().get::<ResolvedClientIp>(),
+            Some(&ResolvedClientIp("198.51.100.17".parse().unwrap()))
+        );
+
+        // info without a client IP does not leave the previous one behind
+        ConnectionInfo::default()
+            .replace_cached(req.head())
+            .unwrap();
+        assert!(req.extensions().get::<ResolvedClientIp>().is_none());
+    }
+
+    #[actix_rt::test]
+    async fn peer_addr_from_middleware() {
+        use actix_service::Service as _;
+
+        use crate::{
+            http::header::HeaderValue,
+            test::{call_service, init_service},
+            web, App, HttpResponse,
+        };
+
+        let srv = init_service(
+            App::new()
+                .app_data(
+                    ConnectionInfoConfig::default()
+                        .synthetic_peer_addr("127.0.0.1:0".parse().unwrap()),
+                )
+                .wrap_fn(|mut req, srv| {
+                    let peer = req.peer_addr().map(|addr| addr.to_string());
+                    let remote = req.connection_info().remote_addr().map(str::to_owned);
+                    let value = format!("{:?} {:?}", peer, remote);
+
+                    req.headers_mut().insert(
+                        HeaderName::from_static("x-mw-peer"),
+                        HeaderValue::from_str(&value).unwrap(),
+                    );
+
+                    srv.call(req)
+                })
+                .default_service(web::to(
+                    |req: HttpRequest, peer: PeerAddr, info: ConnectionInfo| {
+                        let expected = format!(
+                            "{:?} {:?}",
+                            req.peer_addr().map(|addr| addr.to_string()),
+                            Some(peer.0.to_string()),
+                        );
+
+                        assert_eq!(req.headers().get("x-mw-peer").unwrap(), &expected[..]);
+                        assert_eq!(info.remote_addr(), Some(&*peer.0.to_string()));
+
+                        HttpResponse::Ok()
+                    },
+                )),
+        )
+        .await;
+
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+
+        // synthetic fallback is applied in both places
+        let req = TestRequest::default().to_request();
+        let res = call_service(&srv, req).await;
+        assert_eq!(res.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn client_port() {
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:50000".parse().unwrap())
+            .insert_header((header::FORWARDED, r#"for="[2001:db8:cafe::17]:4711""#))
+            .insert_header((X_FORWARDED_PORT, "8443"))
+            .insert_header((header::HOST, "rust-lang.org:8080"))
+            .to_http_request();
+        assert_eq!(req.connection_info().client_port(), Some(4711));
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:50000".parse().unwrap())
+            .insert_header((header::FORWARDED, "for=192.0.2.60"))
+            .insert_header((X_FORWARDED_PORT, "8443"))
+            .insert_header((header::HOST, "rust-lang.org:8080"))
+            .to_http_request();
+        assert_eq!(req.connection_info().client_port(), Some(8443));
+
+        // the port of an X-Forwarded-For node does not take priority
+        for forwarded in &["for=192.0.2.60", "proto=https"] {
+            let req = TestRequest::default()
+                .peer_addr("127.0.0.1:50000".parse().unwrap())
+                .insert_header((header::FORWARDED, *forwarded))
+                .insert_header((X_FORWARDED_FOR, "192.0.2.61:4711"))
+                .insert_header((X_FORWARDED_PORT, "8443"))
+                .to_http_request();
+            assert_eq!(
+                req.connection_info().client_port(),
+                Some(8443),
+                "{}",
+                forwarded
+            );
+        }
+
+        // the port is kept when addresses are anonymized
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, r#"for="192.0.2.60:4711""#))
+            .app_data(ConnectionInfoConfig::default().anonymize_ip(true))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.0"));
+        assert_eq!(info.client_port(), Some(4711));
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:50000".parse().unwrap())
+            .insert_header((X_FORWARDED_PORT, "_hidden"))
+            .insert_header((header::HOST, "rust-lang.org:8080"))
+            .to_http_request();
+        assert_eq!(req.connection_info().client_port(), Some(8080));
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:50000".parse().unwrap())
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+        assert_eq!(req.connection_info().client_port(), Some(50000));
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+        assert_eq!(req.connection_info().client_port(), None);
+    }
+
+    #[actix_rt::test]
+    async fn absolute_uri() {
+        let req = TestRequest::with_uri("/search?q=caf%C3%A9&page=2")
+            .insert_header((header::FORWARDED, "proto=https; host=rust-lang.org"))
+            .to_http_request();
+        let uri = AbsoluteUri::extract(&req).await.unwrap();
+        assert_eq!(
+            uri.0.to_string(),
+            "https://rust-lang.org/search?q=caf%C3%A9&page=2"
+        );
+        assert_eq!(uri.0.query(), Some("q=caf%C3%A9&page=2"));
+
+        let req = TestRequest::with_uri("/docs")
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .insert_header((header::HOST, "rust-lang.org:8443"))
+            .to_http_request();
+        let uri = AbsoluteUri::extract(&req).await.unwrap();
+        assert_eq!(uri.0.to_string(), "https://rust-lang.org:8443/docs");
+        assert_eq!(uri.0.query(), None);
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust lang"))
+            .to_http_request();
+        let err = AbsoluteUri::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ConnectionInfoError::InvalidAbsoluteUri));
+    }
+
+    #[test]
+    fn country_code() {
+        let req = TestRequest::default()
+            .insert_header(("cf-ipcountry", "DE"))
+            .to_http_request();
+        assert_eq!(req.connection_info().country_code(), None);
+
+        let req = TestRequest::default()
+            .insert_header(("cf-ipcountry", "DE"))
+            .app_data(
+                ConnectionInfoConfig::default()
+                    .geo_header(HeaderName::from_static("cf-ipcountry")),
+            )
+            .to_http_request();
+        assert_eq!(req.connection_info().country_code(), Some("DE"));
+
+        let req = TestRequest::default()
+            .insert_header(("x-geo-country", ""))
+            .app_data(
+                ConnectionInfoConfig::default()
+                    .geo_header(HeaderName::from_static("x-geo-country")),
+            )
+            .to_http_request();
+        assert_eq!(req.connection_info().country_code(), None);
+    }
+
+    #[test]
+    fn clf_client() {
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .insert_header((header::FORWARDED, r#"for="[2001:db8::17]:4711""#))
+            .to_http_request();
+        assert_eq!(req.connection_info().clf_client(), "2001:db8::17");
+
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .to_http_request();
+        assert_eq!(req.connection_info().clf_client(), "192.0.2.60");
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.connection_info().clf_client(), "-");
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=unknown"))
+            .to_http_request();
+        assert_eq!(req.connection_info().clf_client(), "-");
+
+        // spoofed header from untrusted peer
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "10.0.0.1"))
+            .app_data(ConnectionInfoConfig::default().trusted_proxies(TrustedProxies::new()))
+            .to_http_request();
+        assert_eq!(req.connection_info().clf_client(), "192.0.2.60");
+    }
+
+    #[test]
+    fn no_forwarded_fast_path() {
+        let cases: &[&[(&str, &str)]] = &[
+            &[("x-forwarded-for", "192.0.2.60")],
+            &[(
+                "x-forwarded-for",
+                " 192.0.2.60 , 198.51.100.17,, 2001:db8::1",
+            )],
+            &[
+                ("x-forwarded-for", "192.0.2.60"),
+                ("x-forwarded-proto", "https"),
+                ("x-forwarded-host", "rust-lang.org"),
+                ("x-forwarded-port", "8443"),
+            ],
+            &[("host", "rust-lang.org:8080")],
+            &[],
+        ];
+
+        for headers in cases {
+            let resolve = |forwarded: Option<&str>| {
+                let mut req =
+                    TestRequest::default().peer_addr("10.0.0.1:4711".parse().unwrap());
+
+                for header in headers.iter() {
+                    req = req.append_header(*header);
+                }
+
+                // an element with only an extension parameter forces the general path but does
+                // not contribute to resolution
+                if let Some(forwarded) = forwarded {
+                    req = req.insert_header((header::FORWARDED, forwarded));
+                }
+
+                req.to_http_request().connection_info().clone()
+            };
+
+            let fast = resolve(None);
+            let general = resolve(Some("ext=1"));
+
+            assert!(!fast.present_forwarding_headers().forwarded);
+            assert!(general.present_forwarding_headers().forwarded);
+
+            assert_eq!(fast.fields(), general.fields(), "{:?}", headers);
+            assert_eq!(
+                fast.scheme_source(),
+                general.scheme_source(),
+                "{:?}",
+                headers
+            );
+            assert_eq!(
+                fast.realip_source(),
+                general.realip_source(),
+                "{:?}",
+                headers
+            );
+            assert_eq!(fast.hop_count(), general.hop_count(), "{:?}", headers);
+            assert_eq!(fast.client_port(), general.client_port(), "{:?}", headers);
+            assert_eq!(fast.proxy_path(), general.proxy_path(), "{:?}", headers);
+            assert_eq!(
+                fast.next_x_forwarded_for("10.0.0.1".parse().unwrap()),
+                general.next_x_forwarded_for("10.0.0.1".parse().unwrap()),
+                "{:?}",
+                headers
+            );
+        }
+    }
+
+    #[test]
+    fn hop_count() {
+        let req = TestRequest::default()
+            .forwarded_chain(&["192.0.2.60", "198.51.100.17", "203.0.113.43"])
+            .to_http_request();
+        assert_eq!(req.connection_info().hop_count(), 3);
+
+        let req = TestRequest::default()
+            .append_header((X_FORWARDED_FOR, "192.0.2.60, 198.51.100.17"))
+            .append_header((X_FORWARDED_FOR, "203.0.113.43"))
+            .to_http_request();
+        assert_eq!(req.connection_info().hop_count(), 3);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.connection_info().hop_count(), 0);
+    }
+
+    #[test]
+    fn aws_alb() {
+        let alb_req = |xff: &str| {
+            TestRequest::default()
+                .peer_addr("10.0.1.23:41320".parse().unwrap())
+                .insert_header((X_FORWARDED_FOR, xff))
+                .insert_header((X_FORWARDED_PROTO, "https"))
+                .insert_header(("x-forwarded-port", "443"))
+                .insert_header((
+                    "x-amzn-trace-id",
+                    "Root=1-67891233-abcdef012345678912345678",
+                ))
+                .app_data(ConnectionInfoConfig::aws_alb())
+        };
+
+        // client connecting directly to the ALB
+        let req = alb_req("203.0.113.7").to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("203.0.113.7"));
+        assert_eq!(info.realip_source(), Some(RealIpSource::XForwardedFor));
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.remote_addr(), Some("10.0.1.23:41320"));
+        drop(info);
+
+        // client-sent entries, spoofed or from a client-side proxy, are left of the ALB's entry
+        let req = alb_req("198.51.100.1, 192.0.2.60, 203.0.113.7").to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("203.0.113.7")
+        );
+
+        let req = alb_req("2001:db8::1, 2001:db8:cafe::17").to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("2001:db8:cafe::17")
+        );
+
+        // passed-through Forwarded header is ignored
+        let req = alb_req("203.0.113.7")
+            .insert_header((header::FORWARDED, "for=198.51.100.1;proto=http"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("203.0.113.7"));
+        assert_eq!(info.scheme(), "https");
+        assert!(info.present_forwarding_headers().forwarded);
+        drop(info);
+
+        // requests bypassing the ALB when its subnets are trusted
+        let req =
+            alb_req("203.0.113.7")
+                .peer_addr("198.51.100.17:50000".parse().unwrap())
+                .app_data(ConnectionInfoConfig::aws_alb().trusted_proxies(
+                    TrustedProxies::new().network("10.0.0.0/16".parse().unwrap()),
+                ))
+                .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("198.51.100.17:50000")
+        );
+    }
+
+    #[test]
+    fn forwarded_header_disabled() {
+        let req = TestRequest::default()
+            .insert_header((
+                header::FORWARDED,
+                "for=192.0.2.60;proto=https;host=rust-lang.org",
+            ))
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17"))
+            .app_data(ConnectionInfoConfig::default().forwarded_header(false))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("198.51.100.17"));
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.host(), "localhost:8080");
+        assert!(info.proxy_path().is_empty());
+    }
+
+    #[test]
+    fn anonymize_ip() {
+        let req = TestRequest::default()
+            .peer_addr("198.51.100.17:50000".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60, _hidden, 203.0.113.43:8080"))
+            .app_data(ConnectionInfoConfig::default().anonymize_ip(true))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.0"));
+        assert_eq!(info.remote_addr(), Some("198.51.100.0"));
+        assert_eq!(info.forwarded_for, &["192.0.2.0", "_hidden", "203.0.113.0"]);
+        assert_eq!(
+            info.next_x_forwarded_for("198.51.100.17".parse().unwrap()),
+            "192.0.2.0, _hidden, 203.0.113.0, 198.51.100.17"
+        );
+        assert_eq!(
+            req.extensions().get::<ResolvedClientIp>().unwrap().0,
+            Ipv4Addr::new(192, 0, 2, 0)
+        );
+
+        // the socket address is dropped from the info but still available from the request
+        assert_eq!(info.peer_socket_addr(), None);
+        assert_eq!(
+            req.peer_addr(),
+            Some("198.51.100.17:50000".parse().unwrap())
+        );
+        drop(info);
+
+        let req = TestRequest::default()
+            .peer_addr("[2001:db8:85a3:1:2:8a2e:370:7334]:8080".parse().unwrap())
+            .insert_header((
+                header::FORWARDED,
+                r#"for="[2001:db8:cafe::17]:4711";by=_lb"#,
+            ))
+            .app_data(ConnectionInfoConfig::default().anonymize_ip(true))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("2001:db8:cafe::"));
+        assert_eq!(info.remote_addr(), Some("2001:db8:85a3::"));
+        assert_eq!(
+            info.proxy_path(),
+            vec![(Some("_lb".to_owned()), Some("2001:db8:cafe::".to_owned()))]
+        );
+        drop(info);
+
+        // no full address is left anywhere, including the raw elements passed on
+        let req = TestRequest::default()
+            .peer_addr("198.51.100.17:50000".parse().unwrap())
+            .insert_header((
+                header::FORWARDED,
+                r#"for=192.0.2.60;by="[2001:db8:cafe::17]:443";ext=1, for=_hidden;by=203.0.113.43"#,
+            ))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60, 203.0.113.43"))
+            .insert_header((header::VIA, "1.1 203.0.113.43:8080"))
+            .app_data(ConnectionInfoConfig::default().anonymize_ip(true))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(
+            info.forwarded_without_last_hop().as_deref(),
+            Some(r#"for=192.0.2.0;by="[2001:db8:cafe::]";ext=1"#)
+        );
+        let debug = format!("{:?}", *info);
+        for full in &[
+            "192.0.2.60",
+            "2001:db8:cafe::17",
+            "203.0.113.43",
+            "198.51.100.17",
+        ] {
+            assert!(!debug.contains(full), "{} in {}", full, debug);
+        }
+        drop(info);
+
+        // disabled by default
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("192.0.2.60")
+        );
+    }
+
+    #[test]
+    fn validate_hop_count() {
+        let req = TestRequest::default()
+            .forwarded_chain(&["192.0.2.60", "198.51.100.17"])
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.validate_hop_count(2), Ok(()));
+        assert_eq!(info.validate_hop_count(5), Ok(()));
+
+        let err = info.validate_hop_count(1).unwrap_err();
+        assert_eq!((err.hops(), err.max()), (2, 1));
+        assert_eq!(
+            err.to_string(),
+            "Forwarded chain has 2 hops, more than the maximum of 1"
+        );
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.connection_info().validate_hop_count(0), Ok(()));
+    }
+
+    #[test]
+    fn realip_of_family() {
+        let req = TestRequest::default()
+            .forwarded_chain(&["unknown", "2001:db8::17", "192.0.2.60", "2001:db8::18"])
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_of_family(true), Some("[2001:db8::17]"));
+        assert_eq!(info.realip_of_family(false), Some("192.0.2.60"));
+
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17, 2001:db8::17"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_of_family(true), Some("2001:db8::17"));
+        assert_eq!(info.realip_of_family(false), Some("198.51.100.17"));
+
+        let req = TestRequest::default()
+            .peer_addr("[::1]:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17"))
+            .to_http_request();
+        assert_eq!(req.connection_info().realip_of_family(true), None);
+    }
+
+    #[cfg(feature = "otel-attributes")]
+    #[test]
+    fn otel_attributes() {
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:50000".parse().unwrap())
+            .insert_header((
+                header::FORWARDED,
+                r#"for="192.0.2.60:4711"; proto=https; host=rust-lang.org:8443"#,
+            ))
+            .to_http_request();
+        let info = req.connection_info();
+        let attrs = info.otel_attributes();
+        let attr = |key| attrs.iter().find(|(k, _)| *k == key).map(|(_, val)| *val);
+
+        assert_eq!(attr("url.scheme"), Some(OtelValue::Str("https")));
+        assert_eq!(
+            attr("server.address"),
+            Some(OtelValue::Str("rust-lang.org"))
+        );
+        assert_eq!(attr("server.port"), Some(OtelValue::Int(8443)));
+        assert_eq!(attr("client.address"), Some(OtelValue::Str("192.0.2.60")));
+        assert_eq!(attr("client.port"), Some(OtelValue::Int(4711)));
+        assert_eq!(
+            attr("network.peer.address"),
+            Some(OtelValue::Str("10.0.0.1"))
+        );
+        assert_eq!(attr("network.peer.port"), Some(OtelValue::Int(50000)));
+        assert_eq!(attrs.len(), 7);
+
+        let req = TestRequest::default()
+            .peer_addr("[2001:db8::2]:50000".parse().unwrap())
+            .insert_header((header::HOST, "[::1]"))
+            .insert_header((header::FORWARDED, r#"for="[2001:db8:cafe::17]""#))
+            .to_http_request();
+        let info = req.connection_info();
+        let attrs = info.otel_attributes();
+        let attr = |key| attrs.iter().find(|(k, _)| *k == key).map(|(_, val)| *val);
+
+        assert_eq!(attr("server.address"), Some(OtelValue::Str("::1")));
+        assert_eq!(attr("server.port"), None);
+        assert_eq!(
+            attr("client.address"),
+            Some(OtelValue::Str("2001:db8:cafe::17"))
+        );
+        assert_eq!(
+            attr("network.peer.address"),
+            Some(OtelValue::Str("2001:db8::2"))
+        );
+        assert_eq!(attr("client.port"), None);
+
+        // proxy and server ports are not the client's
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:50000".parse().unwrap())
+            .insert_header((header::HOST, "rust-lang.org:8443"))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .insert_header((X_FORWARDED_PORT, "443"))
+            .to_http_request();
+        let info = req.connection_info();
+        let attrs = info.otel_attributes();
+        let attr = |key| attrs.iter().find(|(k, _)| *k == key).map(|(_, val)| *val);
+        assert_eq!(attr("client.address"), Some(OtelValue::Str("192.0.2.60")));
+        assert_eq!(attr("client.port"), None);
+        drop(info);
+
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:50000".parse().unwrap())
+            .insert_header((header::HOST, "rust-lang.org:8443"))
+            .to_http_request();
+        let info = req.connection_info();
+        let attrs = info.otel_attributes();
+        let attr = |key| attrs.iter().find(|(k, _)| *k == key).map(|(_, val)| *val);
+        assert_eq!(attr("client.address"), Some(OtelValue::Str("192.0.2.60")));
+        assert_eq!(attr("client.port"), Some(OtelValue::Int(50000)));
+        drop(info);
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "[::1]"))
+            .insert_header((header::FORWARDED, "for=unknown"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().otel_attributes(),
+            vec![
+                ("url.scheme", OtelValue::Str("http")),
+                ("server.address", OtelValue::Str("::1")),
+            ]
+        );
+    }
+
+    #[test]
+    fn next_x_forwarded_for() {
+        let peer = IpAddr::from([198, 51, 100, 17]);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(
+            req.connection_info().next_x_forwarded_for(peer),
+            "198.51.100.17"
+        );
+
+        let req = TestRequest::default()
+            .append_header((X_FORWARDED_FOR, "192.0.2.60,2001:db8::1"))
+            .append_header((X_FORWARDED_FOR, " 203.0.113.5 "))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().next_x_forwarded_for(peer),
+            "192.0.2.60, 2001:db8::1, 203.0.113.5, 198.51.100.17"
+        );
+
+        // independent of the Forwarded header
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=192.0.2.43"))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info()
+                .next_x_forwarded_for("::1".parse().unwrap()),
+            "192.0.2.60, ::1"
+        );
+    }
+
+    #[test]
+    fn peer_socket_addr() {
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(
+            info.peer_socket_addr(),
+            Some(([192, 0, 2, 60], 8080).into())
+        );
+        assert_eq!(info.remote_addr(), Some("192.0.2.60:8080"));
+        assert_eq!(info.redacted().peer_socket_addr(), None);
+
+        let req = TestRequest::default()
+            .peer_addr("[2001:db8::1]:4711".parse().unwrap())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(
+            info.peer_socket_addr(),
+            Some("[2001:db8::1]:4711".parse().unwrap())
+        );
+        assert_eq!(info.remote_addr(), Some("[2001:db8::1]:4711"));
+
+        let req = TestRequest::default()
+            .app_data(
+                ConnectionInfoConfig::default()
+                    .synthetic_peer_addr("127.0.0.1:0".parse().unwrap()),
+            )
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().peer_socket_addr(),
+            Some("127.0.0.1:0".parse().unwrap())
+        );
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.connection_info().peer_socket_addr(), None);
+    }
+
+    #[test]
+    fn remote_alias() {
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.remote(), Some("192.0.2.60"));
+        assert_eq!(info.remote(), info.realip_remote_addr());
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.remote(), Some("127.0.0.1:8080"));
+        assert_eq!(info.remote(), info.realip_remote_addr());
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.connection_info().remote(), None);
+    }
+
+    #[test]
+    fn into_parts() {
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .insert_header((
+                header::FORWARDED,
+                "for=192.0.2.60; proto=https; host=rust-lang.org",
+            ))
+            .to_http_request();
+        let (scheme, host, realip, peer) = req.connection_info().clone().into_parts();
+        assert_eq!(scheme, "https");
+        assert_eq!(host, "rust-lang.org");
+        assert_eq!(realip.as_deref(), Some("192.0.2.60"));
+        assert_eq!(peer.as_deref(), Some("127.0.0.1:8080"));
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .to_http_request();
+        let (_, _, realip, _) = req.connection_info().clone().into_parts();
+        assert_eq!(realip.as_deref(), Some("127.0.0.1:8080"));
+    }
+
+    #[test]
+    fn semantically_eq() {
+        let info = |peer: &str, xff: &str| {
+            let req = TestRequest::default()
+                .peer_addr(peer.parse().unwrap())
+                .insert_header((X_FORWARDED_FOR, xff))
+                .insert_header((header::HOST, "rust-lang.org"))
+                .to_http_request();
+            let info = req.connection_info().clone();
+            info
+        };
+
+        let a = info("127.0.0.1:50000", "192.0.2.60");
+        let b = info("127.0.0.1:50001", "192.0.2.60");
+        assert_ne!(a.remote_addr(), b.remote_addr());
+        assert!(a.semantically_eq(&b));
+
+        assert!(!a.semantically_eq(&info("127.0.0.2:50000", "192.0.2.60")));
+        assert!(!a.semantically_eq(&info("127.0.0.1:50000", "192.0.2.61")));
+
+        // real IP falling back to the peer address
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:50000".parse().unwrap())
+            .to_http_request();
+        let c = req.connection_info().clone();
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:50001".parse().unwrap())
+            .to_http_request();
+        assert!(c.semantically_eq(&req.connection_info()));
+    }
+
+    #[test]
+    fn require_tls_for_forwarding() {
+        let req = |tls: bool| {
+            TestRequest::default()
+                .peer_addr("10.0.0.1:8080".parse().unwrap())
+                .insert_header((header::HOST, "backend.internal"))
+                .insert_header((header::FORWARDED, "for=192.0.2.60;proto=https"))
+                .insert_header((X_FORWARDED_FOR, "192.0.2.61"))
+                .insert_header((X_FORWARDED_HOST, "rust-lang.org"))
+                .insert_header((X_FORWARDED_PORT, "8443"))
+                .tls(tls)
+                .app_data(ConnectionInfoConfig::default().require_tls_for_forwarding(true))
+                .to_http_request()
+        };
+
+        let req_tls = req(true);
+        let info = req_tls.connection_info();
+        assert_eq!(info.scheme_source(), SchemeSource::Forwarded);
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+        assert_eq!(info.client_port(), Some(8443));
+
+        let req_plain = req(false);
+        let info = req_plain.connection_info();
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.host(), "backend.internal");
+        assert_eq!(info.realip_remote_addr(), Some("10.0.0.1:8080"));
+        assert_eq!(info.hop_count(), 0);
+        assert_eq!(info.client_port(), Some(8080));
+        assert_eq!(
+            info.next_x_forwarded_for("10.0.0.1".parse().unwrap()),
+            "10.0.0.1"
+        );
+
+        // honored on plaintext connections by default
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "192.0.2.61"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("192.0.2.61")
+        );
+    }
+
+    #[test]
+    fn internal_node_names() {
+        let info_cfg = ConnectionInfoConfig::default()
+            .trusted_proxies(TrustedProxies::new().network("10.0.0.0/8".parse().unwrap()))
+            .internal_node_names(vec!["ingress-gateway", "sidecar"]);
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .insert_header((
+                header::FORWARDED,
+                r#"for=192.0.2.60, for=Ingress-Gateway, for="sidecar:15001", for=10.0.0.2"#,
+            ))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("192.0.2.60")
+        );
+
+        // unknown names stop the walk like untrusted addresses
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .insert_header((
+                header::FORWARDED,
+                "for=192.0.2.60, for=_hidden, for=ingress-gateway",
+            ))
+            .app_data(info_cfg)
+            .to_http_request();
+        assert_eq!(req.connection_info().realip_remote_addr(), Some("_hidden"));
+
+        // names are not skipped without configuration
+        let req =
+            TestRequest::default()
+                .peer_addr("10.0.0.1:8080".parse().unwrap())
+                .insert_header((header::FORWARDED, "for=192.0.2.60, for=ingress-gateway"))
+                .app_data(ConnectionInfoConfig::default().trusted_proxies(
+                    TrustedProxies::new().network("10.0.0.0/8".parse().unwrap()),
+                ))
+                .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("ingress-gateway")
+        );
+    }
+
+    #[test]
+    fn realip_source() {
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .insert_header((header::FORWARDED, "for=192.0.2.60"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_source(),
+            Some(RealIpSource::Forwarded)
+        );
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_source(),
+            Some(RealIpSource::XForwardedFor)
+        );
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_source(),
+            Some(RealIpSource::Peer)
+        );
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.connection_info().realip_source(), None);
+    }
+
+    #[test]
+    fn realip_source_untrusted_peer() {
+        let info_cfg = ConnectionInfoConfig::default()
+            .trusted_proxies(TrustedProxies::new().network("10.0.0.0/8".parse().unwrap()));
+
+        // spoofed headers from an untrusted peer are ignored
+        let req = TestRequest::default()
+            .peer_addr("203.0.113.5:50000".parse().unwrap())
+            .insert_header((header::FORWARDED, "for=192.0.2.60"))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.61"))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("203.0.113.5:50000"));
+        assert_eq!(info.realip_source(), Some(RealIpSource::Peer));
+        assert!(info
+            .explain()
+            .ends_with("realip=203.0.113.5:50000 (from peer address)"));
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:50000".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "192.0.2.61"))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.61"));
+        assert_eq!(info.realip_source(), Some(RealIpSource::XForwardedFor));
+
+        // no forwarding headers from a trusted peer
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:50000".parse().unwrap())
+            .app_data(info_cfg)
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_source(),
+            Some(RealIpSource::Peer)
+        );
+    }
+
+    #[test]
+    fn trusted_proxy_matched() {
+        let info_cfg = ConnectionInfoConfig::default().trusted_proxies(
+            TrustedProxies::new()
+                .network("10.0.0.0/8".parse().unwrap())
+                .network("172.16.0.0/12".parse().unwrap())
+                .ip("::1".parse().unwrap()),
+        );
+
+        let req = TestRequest::default()
+            .peer_addr("172.20.1.2:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(
+            info.trusted_proxy_matched(),
+            Some(&"172.16.0.0/12".parse().unwrap())
+        );
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+
+        let req = TestRequest::default()
+            .peer_addr("[::1]:8080".parse().unwrap())
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().trusted_proxy_matched(),
+            Some(&"::1/128".parse().unwrap())
+        );
+
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.1:8080".parse().unwrap())
+            .app_data(info_cfg)
+            .to_http_request();
+        assert_eq!(req.connection_info().trusted_proxy_matched(), None);
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .to_http_request();
+        assert_eq!(req.connection_info().trusted_proxy_matched(), None);
+    }
+
+    #[test]
+    fn trusted_proxies_from_str() {
+        let proxies: TrustedProxies = "10.0.0.0/8,::1,192.168.0.0/16".parse().unwrap();
+        assert_eq!(
+            proxies,
+            TrustedProxies::new()
+                .network("10.0.0.0/8".parse().unwrap())
+                .ip("::1".parse().unwrap())
+                .network("192.168.0.0/16".parse().unwrap())
+        );
+
+        let proxies: TrustedProxies = " 10.0.0.1 , ,fd00::/8, ".parse().unwrap();
+        assert!(proxies.contains("10.0.0.1".parse().unwrap()));
+        assert!(proxies.contains("fd00::1".parse().unwrap()));
+        assert!(!proxies.contains("10.0.0.2".parse().unwrap()));
+
+        let err = "10.0.0.0/8,10.0.0.0/33"
+            .parse::<TrustedProxies>()
+            .unwrap_err();
+        assert_eq!(err.entry(), "10.0.0.0/33");
+        assert!("example.com".parse::<TrustedProxies>().is_err());
+
+        assert_eq!("".parse::<TrustedProxies>().unwrap(), TrustedProxies::new());
+        assert_eq!(
+            " ".parse::<TrustedProxies>().unwrap(),
+            TrustedProxies::new()
+        );
+    }
+
+    #[test]
+    fn port_consistent() {
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "example.com:443"))
+            .insert_header((X_FORWARDED_PORT, "443"))
+            .to_http_request();
+        assert!(req.connection_info().port_consistent());
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "example.com:80"))
+            .insert_header((X_FORWARDED_PORT, "443"))
+            .to_http_request();
+        assert!(!req.connection_info().port_consistent());
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "example.com"))
+            .insert_header((X_FORWARDED_PORT, "443"))
+            .to_http_request();
+        assert!(req.connection_info().port_consistent());
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "example.com:80"))
+            .to_http_request();
+        assert!(req.connection_info().port_consistent());
+    }
+
+    #[test]
+    fn client_hint() {
+        let req = TestRequest::default()
+            .insert_header(("sec-ch-ua-platform", r#""Windows""#))
+            .insert_header(("sec-ch-ua-mobile", "?0"))
+            .insert_header((header::USER_AGENT, "test"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.client_hint("Sec-CH-UA-Platform"), Some(r#""Windows""#));
+        assert_eq!(info.client_hint("sec-ch-ua-mobile"), Some("?0"));
+        assert_eq!(info.client_hint("Sec-CH-UA"), None);
+        assert_eq!(info.client_hint("User-Agent"), None);
+    }
+
+    #[test]
+    fn rarely_used_fields_parsed_on_access() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=192.0.2.60;by=_lb, for=_edge"))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .insert_header((header::VIA, "1.1 vegur"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert!(info.lazy.proxy_path.get().is_none());
+        assert!(info.lazy.forwarded_elements.get().is_none());
+        assert!(info.lazy.x_forwarded_for_list.get().is_none());
+        assert!(info.lazy.via_chain.get().is_none());
+
+        assert_eq!(info.via_chain()[0].received_by, "vegur");
+        assert!(info.lazy.via_chain.get().is_some());
+        assert!(info.lazy.proxy_path.get().is_none());
+
+        // parsed fields are cached
+        assert!(ptr::eq(info.via_chain(), info.via_chain()));
+        assert_eq!(info.proxy_path().len(), 2);
+        assert_eq!(
+            info.next_x_forwarded_for(IpAddr::from([198, 51, 100, 17])),
+            "192.0.2.60, 198.51.100.17"
+        );
+
+        // fields parsed before redacting are masked in the copy
+        let redacted = info.redacted();
+        assert_eq!(redacted.via_chain()[0].received_by, "vegur");
+        assert_eq!(
+            redacted.proxy_path()[0],
+            (Some("_lb".to_owned()), Some("192.0.2.0".to_owned()))
+        );
+        assert_eq!(info.proxy_path()[0].1.as_deref(), Some("192.0.2.60"));
+    }
+
+    #[test]
+    fn present_forwarding_headers() {
+        let req = TestRequest::default().to_http_request();
+        assert!(req
+            .connection_info()
+            .present_forwarding_headers()
+            .is_empty());
+
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .insert_header(("x-real-ip", "192.0.2.60"))
+            .to_http_request();
+        let present = req.connection_info().present_forwarding_headers();
+        assert_eq!(
+            present,
+            ForwardingHeadersPresent {
+                forwarded: false,
+                x_forwarded_for: true,
+                x_forwarded_host: false,
+                x_forwarded_proto: true,
+                x_real_ip: true,
+            }
+        );
+        assert!(!present.is_empty());
+
+        // recorded even when empty and unused
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, ""))
+            .to_http_request();
+        assert!(req.connection_info().present_forwarding_headers().forwarded);
+    }
+
+    #[test]
+    fn authority() {
+        let info = |proto: &str, host: &str| {
+            TestRequest::default()
+                .forwarded_proto(proto)
+                .forwarded_host(host)
+                .to_http_request()
+                .connection_info()
+                .authority()
+        };
+
+        assert_eq!(info("https", "rust-lang.org:443"), "rust-lang.org");
+        assert_eq!(info("https", "rust-lang.org"), "rust-lang.org");
+        assert_eq!(info("https", "rust-lang.org:8443"), "rust-lang.org:8443");
+        assert_eq!(info("http", "rust-lang.org:80"), "rust-lang.org");
+        assert_eq!(info("http", "rust-lang.org:443"), "rust-lang.org:443");
+        assert_eq!(info("https", "[::1]:443"), "[::1]");
+        assert_eq!(info("https", "[::1]:8443"), "[::1]:8443");
+    }
+
+    #[test]
+    fn scheme_and_host() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "proto=https; host=rust-lang.org"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme_and_host(), ("https", "rust-lang.org"));
+        assert_eq!(info.scheme_and_host(), (info.scheme(), info.host()));
+    }
+
+    #[cfg(feature = "edge-metadata")]
+    #[test]
+    fn edge_metadata_header() {
+        let edge_meta = HeaderName::from_static("x-edge-meta");
+        let info_cfg = ConnectionInfoConfig::default().edge_metadata_header(edge_meta.clone());
+
+        // {"scheme":"https","host":"www.rust-lang.org","client_ip":"192.0.2.60","pop":"AMS"}
+        let blob =
+            "eyJzY2hlbWUiOiJodHRwcyIsImhvc3QiOiJ3d3cucnVzdC1sYW5nLm9yZyIsImNsaWVudF9pcCI6\
+                    IjE5Mi4wLjIuNjAiLCJwb3AiOiJBTVMifQ==";
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .insert_header((edge_meta.clone(), blob))
+            .insert_header((X_FORWARDED_PROTO, "http"))
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17"))
+            .insert_header((header::HOST, "origin.internal"))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.scheme_source(), SchemeSource::EdgeMetadata);
+        assert_eq!(info.host(), "www.rust-lang.org");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+        assert_eq!(info.realip_source(), Some(RealIpSource::EdgeMetadata));
+        assert!(info.explain().contains("(from edge metadata)"));
+
+        // absent members fall back to the other headers
+        let req = TestRequest::default()
+            .insert_header((edge_meta.clone(), base64::encode(r#"{"scheme": "https"}"#)))
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17"))
+            .insert_header((header::HOST, "origin.internal"))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host(), "origin.internal");
+        assert_eq!(info.realip_remote_addr(), Some("198.51.100.17"));
+
+        // invalid Base64, non-JSON and non-string members are ignored
+        for val in &[
+            "not base64!".to_owned(),
+            base64::encode("not json"),
+            base64::encode(r#"{"scheme": 1}"#),
+        ] {
+            let req = TestRequest::default()
+                .insert_header((edge_meta.clone(), val.as_str()))
+                .insert_header((X_FORWARDED_PROTO, "http"))
+                .app_data(info_cfg.clone())
+                .to_http_request();
+            let info = req.connection_info();
+            assert_eq!(
+                info.scheme_source(),
+                SchemeSource::XForwardedProto,
+                "{}",
+                val
+            );
+        }
+
+        // only honored from trusted proxies when they are configured
+        let info_cfg = info_cfg.trusted_proxies("10.0.0.0/8".parse().unwrap());
+        let req = TestRequest::default()
+            .peer_addr("203.0.113.7:8080".parse().unwrap())
+            .insert_header((edge_meta, blob))
+            .app_data(info_cfg)
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.realip_remote_addr(), Some("203.0.113.7:8080"));
+    }
+
+    #[cfg(feature = "json-realip")]
+    #[test]
+    fn realip_json_header() {
+        let edge_client = HeaderName::from_static("x-edge-client");
+        let info_cfg = ConnectionInfoConfig::default()
+            .realip_json_header(edge_client.clone(), "/client/ip");
+
+        let req = TestRequest::default()
+            .peer_addr("10.0.0.1:8080".parse().unwrap())
+            .insert_header((
+                edge_client.clone(),
+                r#"{"client": {"ip": "192.0.2.60", "port": 4711}, "pop": "AMS"}"#,
+            ))
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17"))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+        assert_eq!(info.realip_source(), Some(RealIpSource::JsonHeader));
+
+        // missing pointer, non-string and non-IP values fall back to the forwarded chain
+        for json in &[
+            r#"{"client": {}}"#,
+            r#"{"client": {"ip": 3221225532}}"#,
+            r#"{"client": {"ip": "example.com"}}"#,
+            "not json",
+        ] {
+            let req = TestRequest::default()
+                .insert_header((edge_client.clone(), *json))
+                .insert_header((X_FORWARDED_FOR, "198.51.100.17"))
+                .app_data(info_cfg.clone())
+                .to_http_request();
+            let info = req.connection_info();
+            assert_eq!(info.realip_remote_addr(), Some("198.51.100.17"), "{}", json);
+            assert_eq!(info.realip_source(), Some(RealIpSource::XForwardedFor));
+        }
+
+        // only honored from trusted proxies when they are configured
+        let info_cfg = info_cfg.trusted_proxies("10.0.0.0/8".parse().unwrap());
+        for (peer, realip) in &[
+            ("10.0.0.1:8080", "192.0.2.60"),
+            ("203.0.113.9:8080", "203.0.113.9:8080"),
+        ] {
+            let req = TestRequest::default()
+                .peer_addr(peer.parse().unwrap())
+                .insert_header((edge_client.clone(), r#"{"client": {"ip": "192.0.2.60"}}"#))
+                .app_data(info_cfg.clone())
+                .to_http_request();
+            assert_eq!(req.connection_info().realip_remote_addr(), Some(*realip));
+        }
+    }
+
+    #[test]
+    fn envoy() {
+        // external request as forwarded by an Envoy edge proxy to a sidecar
+        let envoy_headers = |req: TestRequest| {
+            req.peer_addr("10.1.0.4:51234".parse().unwrap())
+                .insert_header((header::HOST, "api.rust-lang.org"))
+                .insert_header((X_FORWARDED_FOR, "203.0.113.7, 10.1.2.3"))
+                .insert_header((X_FORWARDED_PROTO, "https"))
+                .insert_header(("x-envoy-external-address", "203.0.113.7"))
+                .insert_header(("x-request-id", "8f5f2a3e-5d9a-4c1e-9f0f-1a4a7f0c6d2b"))
+        };
+
+        let req = envoy_headers(TestRequest::default())
+            .app_data(ConnectionInfoConfig::default().envoy(true))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("203.0.113.7"));
+        assert_eq!(info.realip_source(), Some(RealIpSource::Envoy));
+        assert_eq!(info.scheme(), "https");
+
+        // ignored unless enabled
+        let req = envoy_headers(TestRequest::default())
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("198.51.100.17")
+        );
+        let req = envoy_headers(TestRequest::default())
+            .insert_header((X_FORWARDED_FOR, "198.51.100.17"))
+            .app_data(ConnectionInfoConfig::default().envoy(true).envoy(false))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("198.51.100.17")
+        );
+
+        // internal requests resolve as usual
+        let req = TestRequest::default()
+            .peer_addr("10.1.0.4:51234".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "10.1.7.9"))
+            .insert_header(("x-envoy-internal", "true"))
+            .insert_header(("x-envoy-external-address", "203.0.113.7"))
+            .app_data(ConnectionInfoConfig::default().envoy(true))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("10.1.7.9"));
+        assert_eq!(info.realip_source(), Some(RealIpSource::XForwardedFor));
+
+        // custom header name
+        let req = TestRequest::default()
+            .insert_header(("x-mesh-client-address", "2001:db8::7"))
+            .insert_header(("x-envoy-external-address", "203.0.113.7"))
+            .app_data(
+                ConnectionInfoConfig::default().envoy_external_address_header(
+                    HeaderName::from_static("x-mesh-client-address"),
+                ),
+            )
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("2001:db8::7")
+        );
+
+        // only honored from trusted proxies when they are configured
+        let info_cfg = ConnectionInfoConfig::default()
+            .envoy(true)
+            .trusted_proxies("10.0.0.0/8".parse().unwrap());
+        for (peer, realip) in &[
+            ("10.1.0.4:51234", "203.0.113.7"),
+            ("192.0.2.1:4711", "192.0.2.1:4711"),
+        ] {
+            let req = envoy_headers(TestRequest::default())
+                .peer_addr(peer.parse().unwrap())
+                .app_data(info_cfg.clone())
+                .to_http_request();
+            assert_eq!(
+                req.connection_info().realip_remote_addr(),
+                Some(*realip),
+                "{}",
+                peer
+            );
+        }
+    }
+
+    #[test]
+    fn fqdn_host() {
+        for (sent, host) in &[
+            ("example.com.", "example.com"),
+            ("example.com.:8080", "example.com:8080"),
+            ("example.com..", "example.com."),
+            (".", "."),
+        ] {
+            let req = TestRequest::default()
+                .insert_header((header::HOST, *sent))
+                .to_http_request();
+            let info = req.connection_info();
+            assert_eq!(info.host(), *host);
+            assert_eq!(info.raw_host(), *sent);
+        }
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "host=example.com."))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.host(), "example.com");
+        assert!(info.matches_host("example.com"));
+        assert!(info.matches_host("EXAMPLE.com."));
+        assert!(!info.matches_host("www.example.com"));
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "example.com:8080"))
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.raw_host(), "example.com:8080");
+        assert!(info.matches_host("example.com."));
+        assert!(!info.matches_host("example.org"));
+    }
+
+    #[test]
+    fn has_multiple_public_hops() {
+        for (chain, expected) in &[
+            // client behind a corporate proxy, then a CDN edge
+            ("8.8.4.4, 1.1.1.1, 10.0.0.1", true),
+            ("2606:4700::1111, 8.8.4.4", true),
+            ("8.8.4.4, 10.0.0.1, 100.64.0.1", false),
+            // repeated address, including its IPv4-mapped form
+            ("8.8.4.4, ::ffff:8.8.4.4", false),
+            // private, shared, loopback, link-local and documentation ranges are not public
+            (
+                "10.0.0.1, 172.16.0.1, 192.168.0.1, 100.100.0.1, 127.0.0.1",
+                false,
+            ),
+            (
+                "169.254.0.1, fe80::1, fd00::1, ::1, 192.0.2.60, 2001:db8::1",
+                false,
+            ),
+            ("unknown, _hidden", false),
+        ] {
+            let req = TestRequest::default()
+                .insert_header((X_FORWARDED_FOR, *chain))
+                .to_http_request();
+            assert_eq!(
+                req.connection_info().has_multiple_public_hops(),
+                *expected,
+                "{}",
+                chain
+            );
+        }
+
+        let req = TestRequest::default()
+            .peer_addr("1.1.1.1:4711".parse().unwrap())
+            .insert_header((
+                header::FORWARDED,
+                r#"for=8.8.4.4, for="[2606:4700::1111]:443""#,
+            ))
+            .to_http_request();
+        assert!(req.connection_info().has_multiple_public_hops());
+
+        // the peer address is not part of the chain
+        let req = TestRequest::default()
+            .peer_addr("1.1.1.1:4711".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "8.8.4.4"))
+            .to_http_request();
+        assert!(!req.connection_info().has_multiple_public_hops());
+    }
+
+    #[test]
+    fn forwarded_without_last_hop() {
+        let without_last_hop = |headers: &[&'static str]| {
+            let mut req = TestRequest::default();
+            for hdr in headers {
+                req = req.append_header((header::FORWARDED, *hdr));
+            }
+            req.to_http_request()
+                .connection_info()
+                .forwarded_without_last_hop()
+        };
+
+        assert_eq!(
+            without_last_hop(&["for=192.0.2.60;proto=https;ext=1, for=198.51.100.17"])
+                .as_deref(),
+            Some("for=192.0.2.60;proto=https;ext=1"),
+        );
+        assert_eq!(
+            without_last_hop(&["for=192.0.2.60, for=_edge", "for=198.51.100.17;by=_lb"])
+                .as_deref(),
+            Some("for=192.0.2.60, for=_edge"),
+        );
+
+        // a single element, empty elements and no header leave nothing to pass on
+        assert_eq!(without_last_hop(&["for=192.0.2.60"]), None);
+        assert_eq!(without_last_hop(&["for=192.0.2.60, ,"]), None);
+        assert_eq!(without_last_hop(&[]), None);
+    }
+
+    #[test]
+    fn proxy_path() {
+        let req = TestRequest::default()
+            .insert_header((
+                header::FORWARDED,
+                r#"for=192.0.2.60;proto=https;by="203.0.113.43:8080", by=_lb;For=_edge"#,
+            ))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().proxy_path(),
+            vec![
+                (
+                    Some("203.0.113.43:8080".to_owned()),
+                    Some("192.0.2.60".to_owned())
+                ),
+                (Some("_lb".to_owned()), Some("_edge".to_owned())),
+            ]
+        );
+
+        // elements with one or neither node
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=192.0.2.60, proto=https"))
+            .append_header((header::FORWARDED, "by=_lb"))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().proxy_path(),
+            vec![
+                (None, Some("192.0.2.60".to_owned())),
+                (Some("_lb".to_owned()), None),
+            ]
+        );
+
+        let req = TestRequest::default().to_http_request();
+        assert!(req.connection_info().proxy_path().is_empty());
+    }
+
+    #[test]
+    fn scheme_eq() {
+        for proto in &["https", "HTTPS", "Https"] {
+            let req = TestRequest::default()
+                .insert_header((X_FORWARDED_PROTO, *proto))
+                .to_http_request();
+            let info = req.connection_info();
+            assert!(info.scheme_eq("https"), "{}", proto);
+            assert!(info.scheme_eq("HTTPS"), "{}", proto);
+            assert!(info.scheme_eq("hTtPs"), "{}", proto);
+            assert!(!info.scheme_eq("http"), "{}", proto);
+            assert!(!info.scheme_eq("https "), "{}", proto);
+        }
+
+        let req = TestRequest::default().to_http_request();
+        assert!(req.connection_info().scheme_eq("HTTP"));
+    }
+
+    #[test]
+    fn host_is_ip() {
+        for (host, is_ip) in &[
+            ("192.0.2.1", true),
+            ("192.0.2.1:8080", true),
+            ("[::1]", true),
+            ("[::1]:8080", true),
+            ("example.com", false),
+            ("example.com:8080", false),
+        ] {
+            let req = TestRequest::default()
+                .insert_header((header::HOST, *host))
+                .to_http_request();
+            assert_eq!(req.connection_info().host_is_ip(), *is_ip, "{}", host);
+        }
+    }
+
+    #[test]
+    fn split_host_port() {
+        assert_eq!(super::split_host_port("example.com"), ("example.com", None));
+        assert_eq!(
+            super::split_host_port("example.com:8080"),
+            ("example.com", Some("8080"))
+        );
+        assert_eq!(super::split_host_port("[::1]"), ("[::1]", None));
+        assert_eq!(
+            super::split_host_port("[::1]:8080"),
+            ("[::1]", Some("8080"))
+        );
+        assert_eq!(super::split_host_port("::1"), ("::1", None));
+    }
+
+    #[test]
+    fn connection_established_at() {
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.connection_info().connection_established_at(), None);
+
+        let at = Instant::now();
+        let req = TestRequest::default().to_http_request();
+        req.extensions_mut().insert(ConnectionEstablishedAt(at));
+        assert_eq!(req.connection_info().connection_established_at(), Some(at));
+    }
+
+    #[test]
+    fn scheme_is_https_strict() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .insert_header((header::FORWARDED, "proto=https"))
+            .to_http_request();
+        assert_eq!(req.connection_info().scheme(), "https");
+        assert!(!req.connection_info().scheme_is_https_strict(req.head()));
+
+        // any URI scheme can be sent over plaintext
+        let req = TestRequest::get()
+            .uri("https://rust-lang.org/")
+            .to_http_request();
+        assert!(!req.connection_info().scheme_is_https_strict(req.head()));
+
+        let req = |peer: &str| {
+            TestRequest::get()
+                .uri("https://rust-lang.org/")
+                .peer_addr(peer.parse().unwrap())
+                .app_data(
+                    ConnectionInfoConfig::default()
+                        .trusted_proxies(TrustedProxies::new().ip("10.0.0.1".parse().unwrap())),
+                )
+                .to_http_request()
+        };
+        let trusted = req("10.0.0.1:8080");
+        assert!(trusted
+            .connection_info()
+            .scheme_is_https_strict(trusted.head()));
+        let untrusted = req("203.0.113.5:8080");
+        assert!(!untrusted
+            .connection_info()
+            .scheme_is_https_strict(untrusted.head()));
+
+        let req = TestRequest::get()
+            .uri("http://rust-lang.org/")
+            .to_http_request();
+        assert!(!req.connection_info().scheme_is_https_strict(req.head()));
+
+        let req = TestRequest::default().to_http_request();
+        req.extensions_mut().insert(TlsInfo::default());
+        assert!(req.connection_info().scheme_is_https_strict(req.head()));
+
+        let cfg = AppConfig::new(
+            true,
+            "localhost:8443".to_owned(),
+            "127.0.0.1:8443".parse().unwrap(),
+        );
+        let req = TestRequest::default().to_http_request();
+        let info = ConnectionInfo::new(req.head(), &cfg, &DEFAULT_CONFIG);
+        assert!(info.scheme_is_https_strict(req.head()));
+    }
+
+    #[test]
+    fn host_matches_sni() {
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "Rust-Lang.org:443"))
+            .to_http_request();
+        assert_eq!(req.connection_info().host_matches_sni(req.head()), None);
+
+        req.extensions_mut().insert(TlsInfo::default());
+        assert_eq!(req.connection_info().host_matches_sni(req.head()), None);
+
+        req.extensions_mut().insert(TlsInfo {
+            sni: Some("rust-lang.org.".to_owned()),
+            alpn: None,
+        });
+        assert_eq!(
+            req.connection_info().host_matches_sni(req.head()),
+            Some(true)
+        );
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "host=admin.rust-lang.org"))
+            .to_http_request();
+        req.extensions_mut().insert(TlsInfo {
+            sni: Some("cdn.example.com".to_owned()),
+            alpn: None,
+        });
+        assert_eq!(
+            req.connection_info().host_matches_sni(req.head()),
+            Some(false)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn resolved_client_ip() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .to_http_request();
+        assert!(req.extensions().get::<ResolvedClientIp>().is_none());
+
+        let _ = req.connection_info();
+        assert_eq!(
+            req.extensions().get::<ResolvedClientIp>(),
+            Some(&ResolvedClientIp([192, 0, 2, 60].into()))
+        );
+
+        let before = PARSE_COUNT.with(Cell::get);
+        let ip = ResolvedClientIp::extract(&req).await.unwrap();
+        assert_eq!(ip.0, IpAddr::from([192, 0, 2, 60]));
+        assert_eq!(PARSE_COUNT.with(Cell::get), before);
+
+        // resolved on demand
+        let req = TestRequest::default()
+            .peer_addr("[::1]:8080".parse().unwrap())
+            .to_http_request();
+        let ip = ResolvedClientIp::extract(&req).await.unwrap();
+        assert_eq!(ip.0, IpAddr::from(std::net::Ipv6Addr::LOCALHOST));
+
+        let req = TestRequest::default().to_http_request();
+        let err = ResolvedClientIp::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ClientIpError::Missing));
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=unknown"))
+            .to_http_request();
+        let err = ResolvedClientIp::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ClientIpError::Invalid));
+    }
+
+    #[actix_rt::test]
+    async fn sticky_key() {
+        let key = |peer: &str, xff: Option<&str>| {
+            let mut req = TestRequest::default().peer_addr(peer.parse().unwrap());
+            if let Some(xff) = xff {
+                req = req.insert_header((X_FORWARDED_FOR, xff));
+            }
+            let req = req.to_http_request();
+            async move { StickyKey::extract(&req).await.unwrap() }
+        };
+
+        // port is ignored
+        assert_eq!(
+            key("192.0.2.60:1000", None).await,
+            key("192.0.2.60:2000", None).await
+        );
+        assert_eq!(
+            key("10.0.0.1:1000", Some("192.0.2.60")).await,
+            key("192.0.2.60:2000", None).await
+        );
+        assert_eq!(
+            key("[::ffff:192.0.2.60]:1000", None).await,
+            key("192.0.2.60:1000", None).await
+        );
+
+        assert_ne!(
+            key("192.0.2.60:1000", None).await,
+            key("192.0.2.61:1000", None).await
+        );
+        assert_ne!(
+            key("[2001:db8::1]:1000", None).await,
+            key("[2001:db8::2]:1000", None).await
+        );
+
+        // fixed hash function
+        assert_eq!(
+            key("192.0.2.60:1000", None).await,
+            StickyKey(fnv1a([192, 0, 2, 60].iter().copied()))
+        );
+
+        let req = TestRequest::default().to_http_request();
+        let err = StickyKey::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ClientIpError::Missing));
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=unknown"))
+            .to_http_request();
+        let err = StickyKey::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ClientIpError::Invalid));
+    }
+
+    #[test]
+    fn scheme_tls_consistent() {
+        let req = TestRequest::default().to_http_request();
+        assert!(req.connection_info().scheme_tls_consistent(req.head()));
+
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .to_http_request();
+        req.extensions_mut().insert(TlsInfo::default());
+        assert!(req.connection_info().scheme_tls_consistent(req.head()));
+
+        // spoofed secure scheme over plaintext
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .to_http_request();
+        assert!(!req.connection_info().scheme_tls_consistent(req.head()));
+
+        // downgraded scheme over TLS
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_PROTO, "http"))
+            .to_http_request();
+        req.extensions_mut().insert(TlsInfo::default());
+        assert!(!req.connection_info().scheme_tls_consistent(req.head()));
+
+        let req = TestRequest::default().to_http_request();
+        let info = ConnectionInfo::new(
+            req.head(),
+            &AppConfig::new(
+                true,
+                "localhost:8080".to_owned(),
+                "127.0.0.1:8080".parse().unwrap(),
+            ),
+            &DEFAULT_CONFIG,
+        );
+        assert!(info.scheme_tls_consistent(req.head()));
+    }
+
+    #[actix_rt::test]
+    async fn connection_data_extract() {
+        #[derive(Debug, Clone, PartialEq)]
+        struct LocalAddr(&'static str);
+
+        let req = TestRequest::default().to_http_request();
+        let err = ConnectionData::<LocalAddr>::extract(&req)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+
+        // on-connect data only reaches the first request on a connection
+        req.extensions_mut().insert(LocalAddr("10.0.0.1:443"));
+        assert!(ConnectionData::<LocalAddr>::extract(&req).await.is_err());
+
+        let req = TestRequest::default()
+            .conn_data(LocalAddr("10.0.0.1:443"))
+            .to_http_request();
+        let data = ConnectionData::<LocalAddr>::extract(&req).await.unwrap();
+        assert_eq!(*data, LocalAddr("10.0.0.1:443"));
+        assert_eq!(data.into_inner().0, "10.0.0.1:443");
+    }
+
+    #[actix_rt::test]
+    async fn conn_extract() {
+        let req = TestRequest::default()
+            .insert_header((
+                header::FORWARDED,
+                "for=192.0.2.60; proto=https; host=rust-lang.org",
+            ))
+            .to_http_request();
+        let conn = Conn::extract(&req).await.unwrap();
+        assert_eq!(conn.scheme, "https");
+        assert_eq!(conn.host, "rust-lang.org");
+        assert_eq!(conn.client_ip, Some([192, 0, 2, 60].into()));
+
+        let req = TestRequest::default().to_http_request();
+        let conn = Conn::extract(&req).await.unwrap();
+        assert_eq!(conn.scheme, "http");
+        assert_eq!(conn.host, "localhost:8080");
+        assert_eq!(conn.client_ip, None);
+    }
+
+    #[actix_rt::test]
+    async fn strict_conn_info_extract() {
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+        let conn_info = StrictConnectionInfo::extract(&req).await.unwrap();
+        assert_eq!(conn_info.host(), "rust-lang.org");
+
+        let req = TestRequest::default()
+            .append_header((header::HOST, "rust-lang.org"))
+            .append_header((header::HOST, "evil.example"))
+            .to_http_request();
+        let err = StrictConnectionInfo::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ConnectionInfoError::MultipleHostHeaders));
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, ""))
+            .to_http_request();
+        let err = StrictConnectionInfo::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ConnectionInfoError::EmptyForwardedHeader));
+
+        let req = TestRequest::default()
+            .append_header((header::FORWARDED, " , ;"))
+            .append_header((header::FORWARDED, ""))
+            .to_http_request();
+        let err = StrictConnectionInfo::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ConnectionInfoError::EmptyForwardedHeader));
+
+        // validated with the parser used for resolution, which skips pairs without values
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=;proto="))
+            .to_http_request();
+        let err = StrictConnectionInfo::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ConnectionInfoError::EmptyForwardedHeader));
+
+        // lenient extraction proceeds
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, ""))
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .to_http_request();
+        let info = ConnectionInfo::extract(&req).await.unwrap();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+
+        let req = TestRequest::default()
+            .append_header((header::FORWARDED, ""))
+            .append_header((header::FORWARDED, "for=192.0.2.60"))
+            .to_http_request();
+        assert!(StrictConnectionInfo::extract(&req).await.is_ok());
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+
+        // lenient extractor still succeeds
+        assert!(ConnectionInfo::extract(&req).await.is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn try_connection_info() {
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .insert_header((header::FORWARDED, "for=192.0.2.60"))
+            .to_http_request();
+        let info = TryConnectionInfo::extract(&req)
+            .await
+            .unwrap()
+            .into_inner()
+            .unwrap();
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .append_header((header::HOST, "evil.example"))
+            .to_http_request();
+        let res = TryConnectionInfo::extract(&req).await.unwrap().0;
+        assert!(matches!(res, Err(ConnectionInfoError::MultipleHostHeaders)));
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=2001:db8::1"))
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+        let res = TryConnectionInfo::extract(&req).await.unwrap().0;
+        assert!(matches!(res, Err(ConnectionInfoError::UnbracketedIpv6)));
+
+        // the lenient extractor falls back to the server hostname
+        let req = TestRequest::default().to_http_request();
+        let res = TryConnectionInfo::extract(&req).await.unwrap().0;
+        assert!(matches!(res, Err(ConnectionInfoError::MissingHost)));
+        assert_eq!(req.connection_info().host(), "localhost:8080");
+    }
+
+    #[actix_rt::test]
+    async fn certified_host() {
+        let cfg =
+            CertifiedHostConfig::new(vec!["rust-lang.org", "*.Rust-Lang.org", "192.0.2.1"]);
+
+        for host in &[
+            "rust-lang.org",
+            "RUST-LANG.ORG:443",
+            "www.rust-lang.org",
+            "www.rust-lang.org.",
+            "192.0.2.1:8443",
+        ] {
+            let req = TestRequest::default()
+                .insert_header((header::HOST, *host))
+                .app_data(cfg.clone())
+                .to_http_request();
+            let certified = CertifiedHost::extract(&req).await.unwrap();
+            // the trailing dot of a fully qualified host is removed during resolution
+            assert_eq!(certified.0, host.trim_end_matches('.'));
+        }
+
+        for host in &["evil.example", "a.b.rust-lang.org", ".rust-lang.org", "org"] {
+            let req = TestRequest::default()
+                .insert_header((header::HOST, *host))
+                .app_data(cfg.clone())
+                .to_http_request();
+            let err = CertifiedHost::extract(&req).await.unwrap_err();
+            assert_eq!(
+                err.status_code(),
+                StatusCode::MISDIRECTED_REQUEST,
+                "{}",
+                host
+            );
+        }
+
+        // nothing is covered without config
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .to_http_request();
+        assert!(CertifiedHost::extract(&req).await.is_err());
+
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .app_data(Data::new(cfg))
+            .to_http_request();
+        assert!(CertifiedHost::extract(&req).await.is_ok());
+    }
+
+    #[actix_rt::test]
+    async fn unbracketed_ipv6_for() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=2001:db8::1, for=\"[2001:db8::2]\""))
+            .to_http_request();
+
+        // lenient extraction normalizes to the bracketed form
+        let info = ConnectionInfo::extract(&req).await.unwrap();
+        assert_eq!(info.realip_remote_addr(), Some("[2001:db8::1]"));
+        assert_eq!(info.realip_of_family(true), Some("[2001:db8::1]"));
+        assert_eq!(info.hop_count(), 2);
+        assert_eq!(info.clf_client(), "2001:db8::1");
+
+        let err = StrictConnectionInfo::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ConnectionInfoError::UnbracketedIpv6));
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=\"2001:db8::1\""))
+            .to_http_request();
+        let err = StrictConnectionInfo::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ConnectionInfoError::UnbracketedIpv6));
+
+        // quoted-pairs are decoded before validation, as for resolution
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, r#"for="2001:db8::\1""#))
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("[2001:db8::1]")
+        );
+        let err = StrictConnectionInfo::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ConnectionInfoError::UnbracketedIpv6));
+
+        // bracketed nodes and X-Forwarded-For entries are accepted as is
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=\"[2001:db8::1]:4711\""))
+            .to_http_request();
+        let info = StrictConnectionInfo::extract(&req).await.unwrap();
+        assert_eq!(info.realip_remote_addr(), Some("[2001:db8::1]:4711"));
+
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "2001:db8::1"))
+            .to_http_request();
+        let info = StrictConnectionInfo::extract(&req).await.unwrap();
+        assert_eq!(info.realip_remote_addr(), Some("2001:db8::1"));
+    }
+
+    #[test]
+    fn http2_negotiation() {
+        for kind in &[
+            Http2Negotiation::PriorKnowledge,
+            Http2Negotiation::Alpn,
+            Http2Negotiation::Upgrade,
+        ] {
+            let req = TestRequest::default()
+                .version(Version::HTTP_2)
+                .to_http_request();
+            req.extensions_mut().insert(*kind);
+            assert_eq!(req.connection_info().http2_negotiation(), Some(*kind));
+        }
+
+        let req = TestRequest::default()
+            .version(Version::HTTP_2)
+            .to_http_request();
+        assert_eq!(req.connection_info().http2_negotiation(), None);
+
+        let req = TestRequest::default()
+            .version(Version::HTTP_11)
+            .to_http_request();
+        req.extensions_mut().insert(Http2Negotiation::Upgrade);
+        assert_eq!(req.connection_info().http2_negotiation(), None);
+    }
+
+    #[test]
+    fn transport() {
+        for transport in &[
+            Transport::Tcp,
+            Transport::Unix,
+            Transport::Quic,
+            Transport::Unknown,
+        ] {
+            let req = TestRequest::default().to_http_request();
+            req.extensions_mut().insert(*transport);
+            assert_eq!(req.connection_info().transport(), *transport);
+        }
+
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .to_http_request();
+        assert_eq!(req.connection_info().transport(), Transport::Tcp);
+
+        let req = TestRequest::default().to_http_request();
+        assert_eq!(req.connection_info().transport(), Transport::Unknown);
+    }
+
+    #[actix_rt::test]
+    async fn log_client_ip_extract() {
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60:8080"))
+            .to_http_request();
+        let ip = LogClientIp::extract(&req).await.unwrap();
+        assert_eq!(ip.into_inner(), "192.0.2.60");
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, r#"for="[2001:db8:cafe::17]:4711""#))
+            .to_http_request();
+        let ip = LogClientIp::extract(&req).await.unwrap();
+        assert_eq!(ip.into_inner(), "[2001:db8:cafe::17]");
+
+        let req = TestRequest::default()
+            .peer_addr("[::1]:8080".parse().unwrap())
+            .to_http_request();
+        let ip = LogClientIp::extract(&req).await.unwrap();
+        assert_eq!(ip.into_inner(), "[::1]");
+
+        let req = TestRequest::default().to_http_request();
+        let ip = LogClientIp::extract(&req).await.unwrap();
+        assert_eq!(ip.into_inner(), "-");
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=unknown"))
+            .to_http_request();
+        let ip = LogClientIp::extract(&req).await.unwrap();
+        assert_eq!(ip.to_string(), "-");
+    }
+
+    #[actix_rt::test]
+    async fn peer_addr_extract() {
+        let addr = "127.0.0.1:8080".parse().unwrap();
+        let req = TestRequest::default().peer_addr(addr).to_http_request();
+        let peer_addr = PeerAddr::extract(&req).await.unwrap();
+        assert_eq!(peer_addr, PeerAddr(addr));
+
+        let req = TestRequest::default().to_http_request();
+        let res = PeerAddr::extract(&req).await;
+        assert!(res.is_err());
+    }
+
+    #[actix_rt::test]
+    async fn tunnel_info() {
+        let req = TestRequest::default()
+            .method(Method::CONNECT)
+            .uri("rust-lang.org:443")
+            .to_http_request();
+        let tunnel = TunnelInfo::extract(&req).await.unwrap();
+        assert!(tunnel.is_connect);
+        assert_eq!(tunnel.upgrade_protocol, None);
+
+        let req = TestRequest::default()
+            .insert_header((header::CONNECTION, "keep-alive, Upgrade"))
+            .insert_header((header::UPGRADE, "websocket"))
+            .insert_header((header::SEC_WEBSOCKET_VERSION, "13"))
+            .to_http_request();
+        let tunnel = TunnelInfo::extract(&req).await.unwrap();
+        assert!(!tunnel.is_connect);
+        assert_eq!(tunnel.upgrade_protocol.as_deref(), Some("websocket"));
+
+        let req = TestRequest::default()
+            .insert_header((header::CONNECTION, "upgrade"))
+            .insert_header((header::UPGRADE, "h2c, websocket"))
+            .to_http_request();
+        let tunnel = TunnelInfo::extract(&req).await.unwrap();
+        assert_eq!(tunnel.upgrade_protocol.as_deref(), Some("h2c"));
+
+        // Upgrade is only honored when Connection requests it
+        let req = TestRequest::default()
+            .insert_header((header::UPGRADE, "websocket"))
+            .to_http_request();
+        let tunnel = TunnelInfo::extract(&req).await.unwrap();
+        assert_eq!(
+            tunnel,
+            TunnelInfo {
+                is_connect: false,
+                upgrade_protocol: None,
+            }
+        );
+    }
+
+    #[actix_rt::test]
+    async fn quic_peer_addr() {
+        let addr = "192.0.2.60:443".parse().unwrap();
+
+        let req = TestRequest::default()
+            .version(Version::HTTP_3)
+            .to_http_request();
+        req.extensions_mut().insert(QuicPeerAddr(addr));
+
+        assert_eq!(req.peer_addr(), Some(addr));
+        assert_eq!(PeerAddr::extract(&req).await.unwrap(), PeerAddr(addr));
+
+        let info = req.connection_info();
+        assert_eq!(info.remote_addr(), Some("192.0.2.60:443"));
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60:443"));
+        assert_eq!(info.transport(), Transport::Quic);
+        drop(info);
+
+        // socket peer address wins
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .to_http_request();
+        req.extensions_mut().insert(QuicPeerAddr(addr));
+        assert_eq!(req.peer_addr(), Some("127.0.0.1:8080".parse().unwrap()));
+        assert_eq!(req.connection_info().remote_addr(), Some("127.0.0.1:8080"));
+    }
+
+    #[actix_rt::test]
+    async fn synthetic_peer_addr() {
+        let addr = "127.0.0.1:0".parse().unwrap();
+
+        let req = TestRequest::default()
+            .app_data(ConnectionInfoConfig::default().synthetic_peer_addr(addr))
+            .to_http_request();
+        assert_eq!(req.peer_addr(), None);
+        assert_eq!(PeerAddr::extract(&req).await.unwrap(), PeerAddr(addr));
+        assert_eq!(req.connection_info().remote_addr(), Some("127.0.0.1:0"));
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("127.0.0.1:0")
+        );
+
+        // real peer address wins
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .app_data(ConnectionInfoConfig::default().synthetic_peer_addr(addr))
+            .to_http_request();
+        let peer_addr = PeerAddr::extract(&req).await.unwrap();
+        assert_eq!(peer_addr, PeerAddr("192.0.2.60:8080".parse().unwrap()));
+        assert_eq!(req.connection_info().remote_addr(), Some("192.0.2.60:8080"));
+    }
+
+    #[test]
+    fn synthetic_peer_addr_not_trusted() {
+        let addr = "127.0.0.1:0".parse().unwrap();
+        let info_cfg = ConnectionInfoConfig::default()
+            .synthetic_peer_addr(addr)
+            .trusted_proxies(TrustedProxies::new());
+
+        let req = TestRequest::default()
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .insert_header((X_FORWARDED_PROTO, "https"))
+            .insert_header((X_FORWARDED_HOST, "spoofed.example"))
+            .app_data(info_cfg.clone())
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("127.0.0.1:0"));
+        assert_eq!(info.remote_addr(), Some("127.0.0.1:0"));
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.host(), "localhost:8080");
+        assert_eq!(info.trusted_proxy_matched(), None);
+        assert_eq!(
+            info.next_x_forwarded_for(IpAddr::from([127, 0, 0, 1])),
+            "127.0.0.1"
+        );
+
+        // a real loopback peer is still trusted
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .insert_header((X_FORWARDED_FOR, "192.0.2.60"))
+            .app_data(info_cfg)
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("192.0.2.60")
+        );
+    }
+
+    #[test]
+    fn peer_addr_in_net() {
+        let net_v4: IpNet = "192.0.2.0/24".parse().unwrap();
+        let net_v6: IpNet = "2001:db8::/32".parse().unwrap();
+
+        let peer = PeerAddr("192.0.2.60:8080".parse().unwrap());
+        assert!(peer.is_in(&net_v4));
+        assert!(!peer.is_in(&net_v6));
+        assert!(!peer.is_loopback());
+
+        let peer = PeerAddr("198.51.100.17:8080".parse().unwrap());
+        assert!(!peer.is_in(&net_v4));
+
+        let peer = PeerAddr("[2001:db8:cafe::17]:4711".parse().unwrap());
+        assert!(peer.is_in(&net_v6));
+        assert!(!peer.is_in(&net_v4));
+        assert!(!peer.is_loopback());
+
+        let peer = PeerAddr("[2001:db9::1]:4711".parse().unwrap());
+        assert!(!peer.is_in(&net_v6));
+
+        assert!(PeerAddr("127.0.0.1:8080".parse().unwrap()).is_loopback());
+        assert!(PeerAddr("[::1]:8080".parse().unwrap()).is_loopback());
+    }
+
+    #[actix_rt::test]
+    async fn required_client_ip_extract() {
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, r#"for="[2001:db8:cafe::17]:4711""#))
+            .to_http_request();
+        let ip = RequiredClientIp::extract(&req).await.unwrap();
+        assert_eq!(
+            ip.into_inner(),
+            "2001:db8:cafe::17".parse::<IpAddr>().unwrap()
+        );
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .to_http_request();
+        let ip = RequiredClientIp::extract(&req).await.unwrap();
+        assert_eq!(ip, RequiredClientIp([127, 0, 0, 1].into()));
+
+        let req = TestRequest::default().to_http_request();
+        let err = RequiredClientIp::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ClientIpError::Missing));
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+
+        let req = TestRequest::default()
+            .insert_header((header::FORWARDED, "for=unknown"))
+            .to_http_request();
+        let err = RequiredClientIp::extract(&req).await.unwrap_err();
+        assert!(matches!(err, ClientIpError::Invalid));
+        assert_eq!(err.status_code(), StatusCode::BAD_REQUEST);
+    }
+}
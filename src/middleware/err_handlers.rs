@@ -33,6 +33,10 @@ type ErrorHandler<B> = dyn Fn(ServiceResponse<B>) -> Result<ErrorHandlerResponse
 /// Register handlers with the `ErrorHandlers::handler()` method to register a custom error handler
 /// for a given status code. Handlers can modify existing responses or create completely new ones.
 ///
+/// Handlers can inspect the request through [`ServiceResponse::request`], eg. to log the client
+/// address with [`connection_info`](crate::HttpRequest::connection_info). Connection info resolved
+/// earlier in the request, such as by an extractor, is reused rather than resolved again.
+///
 /// # Examples
 /// ```
 /// use actix_web::middleware::{ErrorHandlers, ErrorHandlerResponse};
@@ -232,4 +236,34 @@ mod tests {
         let resp = test::call_service(&mw, TestRequest::default().to_srv_request()).await;
         assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "0001");
     }
+
+    #[actix_rt::test]
+    async fn test_handler_connection_info() {
+        #[allow(clippy::unnecessary_wraps)]
+        fn log_client<B>(mut res: ServiceResponse<B>) -> Result<ErrorHandlerResponse<B>> {
+            let client = res.request().connection_info().clf_client();
+            res.response_mut()
+                .headers_mut()
+                .insert(CONTENT_TYPE, HeaderValue::from_str(&client).unwrap());
+            Ok(ErrorHandlerResponse::Response(res))
+        }
+
+        let srv = |req: ServiceRequest| {
+            assert_eq!(req.connection_info().host(), "rust-lang.org");
+            ok(req.into_response(HttpResponse::InternalServerError().finish()))
+        };
+
+        let mw = ErrorHandlers::new()
+            .handler(StatusCode::INTERNAL_SERVER_ERROR, log_client)
+            .new_transform(srv.into_service())
+            .await
+            .unwrap();
+
+        let req = TestRequest::default()
+            .insert_header(("host", "rust-lang.org"))
+            .insert_header(("x-forwarded-for", "192.0.2.60"))
+            .to_srv_request();
+        let resp = test::call_service(&mw, req).await;
+        assert_eq!(resp.headers().get(CONTENT_TYPE).unwrap(), "192.0.2.60");
+    }
 }
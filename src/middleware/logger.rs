@@ -78,6 +78,12 @@ use crate::{
 ///
 /// If you use this value, ensure that all requests come from trusted hosts. Otherwise, it is
 /// trivial for the remote client to falsify their source IP address.
+///
+/// The value is resolved with the app's
+/// [`ConnectionInfoConfig`](crate::dev::ConnectionInfoConfig), so when
+/// [trusted proxies](crate::dev::ConnectionInfoConfig::trusted_proxies) are configured, forwarding
+/// headers from untrusted peers are ignored and `%{r}a` logs the client closest to the server
+/// that is not one of the trusted proxies.
 #[derive(Debug)]
 pub struct Logger(Rc<Inner>);
 
@@ -804,6 +810,45 @@ mod tests {
         assert!(s.contains("192.0.2.60"));
     }
 
+    #[actix_rt::test]
+    async fn test_remote_addr_format_trusted_proxies() {
+        use crate::dev::{ConnectionInfoConfig, TrustedProxies};
+
+        let render_line = |peer: &str| {
+            let mut format = Format::new("%a %{r}a");
+
+            let req = TestRequest::default()
+                .peer_addr(peer.parse().unwrap())
+                .insert_header(("x-forwarded-for", "203.0.113.7, 192.0.2.60"))
+                .app_data(ConnectionInfoConfig::default().trusted_proxies(
+                    TrustedProxies::new().network("10.0.0.0/8".parse().unwrap()),
+                ))
+                .to_srv_request();
+
+            let now = OffsetDateTime::now_utc();
+            for unit in &mut format.0 {
+                unit.render_request(now, &req);
+            }
+
+            let render = |fmt: &mut fmt::Formatter<'_>| {
+                for unit in &format.0 {
+                    unit.render(fmt, 1024, now)?;
+                }
+                Ok(())
+            };
+            format!("{}", FormatDisplay(&render))
+        };
+
+        // closest untrusted hop behind a trusted proxy
+        assert_eq!(render_line("10.0.0.1:8080"), "10.0.0.1:8080 192.0.2.60");
+
+        // forwarding headers from untrusted peers are ignored
+        assert_eq!(
+            render_line("198.51.100.1:8080"),
+            "198.51.100.1:8080 198.51.100.1:8080"
+        );
+    }
+
     #[actix_rt::test]
     async fn test_custom_closure_log() {
         let mut logger = Logger::new("test %{CUSTOM}xi")
@@ -16,8 +16,12 @@ use cookie::{Cookie, ParseError as CookieParseError};
 use smallvec::SmallVec;
 
 use crate::{
-    app_service::AppInitServiceState, config::AppConfig, error::UrlGenerationError,
-    info::ConnectionInfo, rmap::ResourceMap, Error, FromRequest,
+    app_service::AppInitServiceState,
+    config::AppConfig,
+    error::UrlGenerationError,
+    info::{connection_peer_addr, ConnectionInfo, ConnectionInfoConfig},
+    rmap::ResourceMap,
+    Error, FromRequest,
 };
 
 #[cfg(feature = "cookies")]
@@ -161,6 +165,15 @@ impl HttpRequest {
         self.head().extensions_mut()
     }
 
+    /// Returns a reference to connection data of type `T` set by
+    /// [`HttpServer::on_connect_conn_data`](crate::HttpServer::on_connect_conn_data).
+    ///
+    /// Connection data is shared by all requests on the same connection.
+    #[inline]
+    pub fn conn_data<T: 'static>(&self) -> Option<&T> {
+        self.head().conn_data()?.get()
+    }
+
     /// Generate url for named resource
     ///
     /// ```
@@ -209,10 +222,13 @@ impl HttpRequest {
     ///
     /// To get client connection information `.connection_info()` should be used.
     ///
+    /// For connections without a socket peer address, eg. QUIC, the address is taken from
+    /// [`QuicPeerAddr`](crate::dev::QuicPeerAddr) connection data.
+    ///
     /// Will only return None when called in unit tests.
     #[inline]
     pub fn peer_addr(&self) -> Option<net::SocketAddr> {
-        self.head().peer_addr
+        connection_peer_addr(self.head())
     }
 
     /// Get *ConnectionInfo* for the current request.
@@ -221,7 +237,37 @@ impl HttpRequest {
     /// borrowed.
     #[inline]
     pub fn connection_info(&self) -> Ref<'_, ConnectionInfo> {
-        ConnectionInfo::get(self.head(), self.app_config())
+        let info_cfg = ConnectionInfoConfig::from_req(self);
+        ConnectionInfo::get_with_config(self.head(), self.app_config(), info_cfg)
+    }
+
+    /// Get an owned copy of the *ConnectionInfo* for the current request.
+    ///
+    /// Unlike [`connection_info`](Self::connection_info), the result does not borrow the request's
+    /// extensions container, so it can be stored or moved, eg. into a struct or another task.
+    /// Resolves identically to, and shares the cached value with, `connection_info`.
+    ///
+    /// ```
+    /// use actix_web::{dev::ConnectionInfo, test::TestRequest};
+    ///
+    /// struct AuditEntry {
+    ///     info: ConnectionInfo,
+    /// }
+    ///
+    /// let req = TestRequest::default()
+    ///     .insert_header(("x-forwarded-for", "192.0.2.60"))
+    ///     .to_http_request();
+    ///
+    /// let entry = AuditEntry {
+    ///     info: req.connection_info_cloned(),
+    /// };
+    /// drop(req);
+    ///
+    /// assert_eq!(entry.info.realip_remote_addr(), Some("192.0.2.60"));
+    /// ```
+    #[inline]
+    pub fn connection_info_cloned(&self) -> ConnectionInfo {
+        self.connection_info().clone()
     }
 
     /// App config
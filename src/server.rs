@@ -19,7 +19,7 @@ use actix_tls::accept::openssl::{AlpnError, SslAcceptor, SslAcceptorBuilder};
 #[cfg(feature = "rustls")]
 use actix_tls::accept::rustls::ServerConfig as RustlsServerConfig;
 
-use crate::{config::AppConfig, Error};
+use crate::{config::AppConfig, info::ConnectionConstants, Error};
 
 struct Socket {
     scheme: &'static str,
@@ -66,6 +66,7 @@ where
     sockets: Vec<Socket>,
     builder: ServerBuilder,
     on_connect_fn: Option<Arc<dyn Fn(&dyn Any, &mut Extensions) + Send + Sync>>,
+    on_connect_conn_data_fn: Option<Arc<dyn Fn(&dyn Any, &mut Extensions) + Send + Sync>>,
     _phantom: PhantomData<(S, B)>,
 }
 
@@ -99,6 +100,7 @@ where
             sockets: Vec::new(),
             builder: ServerBuilder::default(),
             on_connect_fn: None,
+            on_connect_conn_data_fn: None,
             _phantom: PhantomData,
         }
     }
@@ -106,6 +108,8 @@ where
     /// Sets function that will be called once before each connection is handled.
     /// It will receive a `&std::any::Any`, which contains underlying connection type and an
     /// [Extensions] container so that request-local data can be passed to middleware and handlers.
+    /// The data is merged into the extensions of the first request on the connection; use
+    /// [`on_connect_conn_data`](Self::on_connect_conn_data) for data shared by all its requests.
     ///
     /// For example:
     /// - `actix_tls::openssl::SslStream<actix_web::rt::net::TcpStream>` when using openssl.
@@ -124,10 +128,29 @@ where
             sockets: self.sockets,
             builder: self.builder,
             on_connect_fn: Some(Arc::new(f)),
+            on_connect_conn_data_fn: self.on_connect_conn_data_fn,
             _phantom: PhantomData,
         }
     }
 
+    /// Sets function that will be called once before each connection is handled, to set
+    /// connection data.
+    ///
+    /// Receives the same arguments as the [`on_connect`](Self::on_connect) callback, which runs
+    /// first. Unlike its data, which is merged into request extensions, the values inserted here
+    /// are shared by all requests on the connection; read them with
+    /// [`HttpRequest::conn_data`](crate::HttpRequest::conn_data) or the
+    /// [`ConnectionData`](crate::dev::ConnectionData) extractor.
+    pub fn on_connect_conn_data<CB>(self, f: CB) -> HttpServer<F, I, S, B>
+    where
+        CB: Fn(&dyn Any, &mut Extensions) + Send + Sync + 'static,
+    {
+        HttpServer {
+            on_connect_conn_data_fn: Some(Arc::new(f)),
+            ..self
+        }
+    }
+
     /// Set number of workers to start.
     ///
     /// By default, server uses number of available logical CPU as thread count.
@@ -284,7 +307,7 @@ where
             addr,
             scheme: "http",
         });
-        let on_connect_fn = self.on_connect_fn.clone();
+        let on_connect_fn = self.on_connect_handler();
 
         self.builder =
             self.builder
@@ -298,8 +321,8 @@ where
                         .local_addr(addr);
 
                     if let Some(handler) = on_connect_fn.clone() {
-                        svc = svc.on_connect_ext(move |io: &_, ext: _| {
-                            (handler)(io as &dyn Any, ext)
+                        svc = svc.on_connect_conn_data(move |io: &_, ext: _, conn_data: _| {
+                            (handler)(io as &dyn Any, ext, conn_data)
                         })
                     };
 
@@ -341,7 +364,7 @@ where
             scheme: "https",
         });
 
-        let on_connect_fn = self.on_connect_fn.clone();
+        let on_connect_fn = self.on_connect_handler();
 
         self.builder =
             self.builder
@@ -355,8 +378,8 @@ where
                         .client_disconnect(c.client_shutdown);
 
                     let svc = if let Some(handler) = on_connect_fn.clone() {
-                        svc.on_connect_ext(move |io: &_, ext: _| {
-                            (&*handler)(io as &dyn Any, ext)
+                        svc.on_connect_conn_data(move |io: &_, ext: _, conn_data: _| {
+                            (handler)(io as &dyn Any, ext, conn_data)
                         })
                     } else {
                         svc
@@ -401,7 +424,7 @@ where
             scheme: "https",
         });
 
-        let on_connect_fn = self.on_connect_fn.clone();
+        let on_connect_fn = self.on_connect_handler();
 
         self.builder =
             self.builder
@@ -415,7 +438,9 @@ where
                         .client_disconnect(c.client_shutdown);
 
                     let svc = if let Some(handler) = on_connect_fn.clone() {
-                        svc.on_connect_ext(move |io: &_, ext: _| (handler)(io as &dyn Any, ext))
+                        svc.on_connect_conn_data(move |io: &_, ext: _, conn_data: _| {
+                            (handler)(io as &dyn Any, ext, conn_data)
+                        })
                     } else {
                         svc
                     };
@@ -524,7 +549,7 @@ where
         });
 
         let addr = format!("actix-web-service-{:?}", lst.local_addr()?);
-        let on_connect_fn = self.on_connect_fn.clone();
+        let on_connect_fn = self.on_connect_handler();
 
         self.builder = self.builder.listen_uds(addr, lst, move || {
             let c = cfg.lock().unwrap();
@@ -540,8 +565,9 @@ where
                     .client_timeout(c.client_timeout);
 
                 if let Some(handler) = on_connect_fn.clone() {
-                    svc = svc
-                        .on_connect_ext(move |io: &_, ext: _| (&*handler)(io as &dyn Any, ext));
+                    svc = svc.on_connect_conn_data(move |io: &_, ext: _, conn_data: _| {
+                        (handler)(io as &dyn Any, ext, conn_data)
+                    });
                 }
 
                 let fac = factory()
@@ -612,6 +638,33 @@ where
     S::Service: 'static,
     B: MessageBody,
 {
+    /// Combines the on-connect callbacks; `None` if neither is set.
+    ///
+    /// The connection-constant inputs of connection info are recorded after the callbacks run, so
+    /// they are not looked up again for each request on the connection.
+    fn on_connect_handler(
+        &self,
+    ) -> Option<Arc<dyn Fn(&dyn Any, &mut Extensions, &mut Extensions) + Send + Sync>> {
+        if self.on_connect_fn.is_none() && self.on_connect_conn_data_fn.is_none() {
+            return None;
+        }
+
+        let on_connect = self.on_connect_fn.clone();
+        let on_connect_conn_data = self.on_connect_conn_data_fn.clone();
+
+        Some(Arc::new(move |io, data, conn_data| {
+            if let Some(ref handler) = on_connect {
+                handler(io, data);
+            }
+
+            if let Some(ref handler) = on_connect_conn_data {
+                handler(io, conn_data);
+            }
+
+            ConnectionConstants::record(data, conn_data);
+        }))
+    }
+
     /// Start listening for incoming connections.
     ///
     /// This method starts number of HTTP workers in separate threads.
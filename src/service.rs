@@ -16,7 +16,7 @@ use crate::{
     config::{AppConfig, AppService},
     dev::insert_slash,
     guard::Guard,
-    info::ConnectionInfo,
+    info::{connection_peer_addr, ConnectionInfo},
     rmap::ResourceMap,
     Error, HttpRequest, HttpResponse,
 };
@@ -177,16 +177,21 @@ impl ServiceRequest {
     ///
     /// To get client connection information `ConnectionInfo` should be used.
     ///
-    /// Will only return None when called in unit tests.
+    /// Will only return None when called in unit tests. Same as [`HttpRequest::peer_addr`]; use
+    /// [`connection_info`](Self::connection_info) to also respect a configured
+    /// [synthetic peer address](crate::dev::ConnectionInfoConfig::synthetic_peer_addr).
     #[inline]
     pub fn peer_addr(&self) -> Option<net::SocketAddr> {
-        self.head().peer_addr
+        connection_peer_addr(self.head())
     }
 
     /// Get *ConnectionInfo* for the current request.
+    ///
+    /// Resolves identically to, and shares the cached value with,
+    /// [`HttpRequest::connection_info`].
     #[inline]
     pub fn connection_info(&self) -> Ref<'_, ConnectionInfo> {
-        ConnectionInfo::get(self.head(), &*self.app_config())
+        self.req.connection_info()
     }
 
     /// Get a reference to the Path parameters.
@@ -23,7 +23,11 @@ use crate::{
     config::AppConfig,
     data::Data,
     dev::{Body, MessageBody, Payload},
-    http::header::ContentType,
+    http::{
+        header::{self, ContentType},
+        Forwarded, ForwardedElement,
+    },
+    info::{ConnectionConstants, ConnectionInfoOverrides, TlsInfo},
     rmap::ResourceMap,
     service::{ServiceRequest, ServiceResponse},
     web::{Bytes, BytesMut},
@@ -376,10 +380,53 @@ pub struct TestRequest {
     path: Path<Url>,
     peer_addr: Option<SocketAddr>,
     app_data: Extensions,
+    forwarded: ForwardedParams,
+    info_overrides: ConnectionInfoOverrides,
+    conn_data: Option<Extensions>,
     #[cfg(feature = "cookies")]
     cookies: CookieJar,
 }
 
+/// Parameters used to build a `Forwarded` header in [`TestRequest::finish`].
+#[derive(Default)]
+struct ForwardedParams {
+    chain: Vec<String>,
+    proto: Option<String>,
+    host: Option<String>,
+}
+
+impl ForwardedParams {
+    fn is_empty(&self) -> bool {
+        self.chain.is_empty() && self.proto.is_none() && self.host.is_none()
+    }
+
+    /// Builds the header with proto and host attached to the first element.
+    fn to_forwarded(&self) -> Forwarded {
+        let mut first = ForwardedElement::default();
+
+        if let Some(node) = self.chain.first() {
+            first.set_for_node(node.as_str());
+        }
+        if let Some(ref proto) = self.proto {
+            first.set_proto(proto.as_str());
+        }
+        if let Some(ref host) = self.host {
+            first.set_host(host.as_str());
+        }
+
+        let mut forwarded = Forwarded::new();
+        forwarded.push(first);
+
+        for node in self.chain.iter().skip(1) {
+            let mut elem = ForwardedElement::default();
+            elem.set_for_node(node.as_str());
+            forwarded.push(elem);
+        }
+
+        forwarded
+    }
+}
+
 impl Default for TestRequest {
     fn default() -> TestRequest {
         TestRequest {
@@ -389,6 +436,9 @@ impl Default for TestRequest {
             path: Path::new(Url::new(Uri::default())),
             peer_addr: None,
             app_data: Extensions::new(),
+            forwarded: ForwardedParams::default(),
+            info_overrides: ConnectionInfoOverrides::default(),
+            conn_data: None,
             #[cfg(feature = "cookies")]
             cookies: CookieJar::new(),
         }
@@ -482,6 +532,145 @@ impl TestRequest {
         self
     }
 
+    /// Set a `Forwarded` header listing the given `for` nodes, client first and proxies after.
+    ///
+    /// Node values are quoted and IPv6 addresses bracketed as necessary. Combine with
+    /// [`forwarded_proto`](Self::forwarded_proto) and [`forwarded_host`](Self::forwarded_host) to
+    /// add those parameters to the first element.
+    ///
+    /// ```
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default()
+    ///     .forwarded_chain(&["192.0.2.60", "2001:db8::17", "203.0.113.43:8080"])
+    ///     .forwarded_proto("https")
+    ///     .to_http_request();
+    ///
+    /// assert_eq!(
+    ///     req.headers().get("forwarded").unwrap(),
+    ///     r#"for=192.0.2.60;proto=https, for="[2001:db8::17]", for="203.0.113.43:8080""#,
+    /// );
+    /// assert_eq!(req.connection_info().realip_remote_addr(), Some("192.0.2.60"));
+    /// assert_eq!(req.connection_info().scheme(), "https");
+    /// ```
+    pub fn forwarded_chain(mut self, chain: &[&str]) -> Self {
+        self.forwarded.chain = chain.iter().map(|node| (*node).to_owned()).collect();
+        self
+    }
+
+    /// Set `proto` parameter of the `Forwarded` header. See
+    /// [`forwarded_chain`](Self::forwarded_chain).
+    pub fn forwarded_proto(mut self, proto: &str) -> Self {
+        self.forwarded.proto = Some(proto.to_owned());
+        self
+    }
+
+    /// Set `host` parameter of the `Forwarded` header. See
+    /// [`forwarded_chain`](Self::forwarded_chain).
+    pub fn forwarded_host(mut self, host: &str) -> Self {
+        self.forwarded.host = Some(host.to_owned());
+        self
+    }
+
+    /// Force the [scheme](crate::dev::ConnectionInfo::scheme) of the request's connection info,
+    /// regardless of headers and server config.
+    ///
+    /// ```
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default()
+    ///     .insert_header(("x-forwarded-proto", "https"))
+    ///     .force_scheme("http")
+    ///     .to_http_request();
+    ///
+    /// assert_eq!(req.connection_info().scheme(), "http");
+    /// ```
+    pub fn force_scheme(mut self, scheme: &str) -> Self {
+        self.info_overrides.scheme = Some(scheme.to_owned());
+        self
+    }
+
+    /// Force the [host](crate::dev::ConnectionInfo::host) of the request's connection info,
+    /// regardless of headers and server config.
+    pub fn force_host(mut self, host: &str) -> Self {
+        self.info_overrides.host = Some(host.to_owned());
+        self
+    }
+
+    /// Force the [real IP](crate::dev::ConnectionInfo::realip_remote_addr) of the request's
+    /// connection info, regardless of headers and peer address.
+    ///
+    /// When any value is forced, the connection info is resolved and cached as the request is
+    /// built, with the config set through [`app_data`](Self::app_data).
+    pub fn force_realip(mut self, realip: &str) -> Self {
+        self.info_overrides.realip = Some(realip.to_owned());
+        self
+    }
+
+    /// Mark the request's connection as TLS by setting [`TlsInfo`] connection data, as a TLS
+    /// acceptor's [`on_connect_conn_data`](crate::HttpServer::on_connect_conn_data) callback would.
+    ///
+    /// Passing false removes the connection data, including any [SNI](Self::sni) and
+    /// [ALPN](Self::alpn) values.
+    ///
+    /// ```
+    /// use actix_web::{dev::SchemeSource, test::TestRequest};
+    ///
+    /// let req = TestRequest::default().tls(true).to_http_request();
+    /// let info = req.connection_info();
+    ///
+    /// assert_eq!(info.scheme(), "https");
+    /// assert_eq!(info.scheme_source(), SchemeSource::Tls);
+    /// ```
+    pub fn tls(mut self, tls: bool) -> Self {
+        if tls {
+            self.tls_info();
+        } else if let Some(ref mut conn_data) = self.conn_data {
+            conn_data.remove::<TlsInfo>();
+        }
+        self
+    }
+
+    /// Set the TLS server name (SNI) of the request's connection. Implies [`tls(true)`](Self::tls).
+    pub fn sni(mut self, sni: &str) -> Self {
+        self.tls_info().sni = Some(sni.to_owned());
+        self
+    }
+
+    /// Set the ALPN protocol negotiated on the request's connection, eg. `h2`. Implies
+    /// [`tls(true)`](Self::tls).
+    pub fn alpn(mut self, alpn: &str) -> Self {
+        self.tls_info().alpn = Some(alpn.to_owned());
+        self
+    }
+
+    /// The `TlsInfo` connection data, inserted if not set yet.
+    fn tls_info(&mut self) -> &mut TlsInfo {
+        let conn_data = self.conn_data.get_or_insert_with(Extensions::new);
+
+        if !conn_data.contains::<TlsInfo>() {
+            conn_data.insert(TlsInfo::default());
+        }
+
+        conn_data.get_mut::<TlsInfo>().unwrap()
+    }
+
+    /// Insert a value into the connection data of the request's connection, as an
+    /// [`on_connect_conn_data`](crate::HttpServer::on_connect_conn_data) callback would.
+    ///
+    /// ```
+    /// use actix_web::test::TestRequest;
+    ///
+    /// let req = TestRequest::default().conn_data(42u32).to_http_request();
+    /// assert_eq!(req.conn_data::<u32>(), Some(&42));
+    /// ```
+    pub fn conn_data<T: 'static>(mut self, data: T) -> Self {
+        self.conn_data
+            .get_or_insert_with(Extensions::new)
+            .insert(data);
+        self
+    }
+
     /// Set request payload
     pub fn set_payload<B: Into<Bytes>>(mut self, data: B) -> Self {
         self.req.set_payload(data);
@@ -529,9 +718,19 @@ impl TestRequest {
     }
 
     fn finish(&mut self) -> Request {
-        // mut used when cookie feature is enabled
-        #[allow(unused_mut)]
+        if !self.forwarded.is_empty() {
+            let forwarded = self.forwarded.to_forwarded();
+            self.req.insert_header((header::FORWARDED, forwarded));
+        }
+
+        if let Some(mut conn_data) = self.conn_data.take() {
+            // recorded as by HttpServer for connections with an on-connect callback
+            ConnectionConstants::record(&Extensions::new(), &mut conn_data);
+            self.req.conn_data(conn_data);
+        }
+
         let mut req = self.req.finish();
+        req.head_mut().peer_addr = self.peer_addr;
 
         #[cfg(feature = "cookies")]
         {
@@ -551,20 +750,22 @@ impl TestRequest {
             }
         }
 
+        if !self.info_overrides.is_empty() {
+            self.info_overrides
+                .resolve_cached(req.head(), &self.config, &self.app_data);
+        }
+
         req
     }
 
     /// Complete request creation and generate `Request` instance
     pub fn to_request(mut self) -> Request {
-        let mut req = self.finish();
-        req.head_mut().peer_addr = self.peer_addr;
-        req
+        self.finish()
     }
 
     /// Complete request creation and generate `ServiceRequest` instance
     pub fn to_srv_request(mut self) -> ServiceRequest {
-        let (mut head, payload) = self.finish().into_parts();
-        head.peer_addr = self.peer_addr;
+        let (head, payload) = self.finish().into_parts();
         self.path.get_mut().update(&head.uri);
 
         let app_state = AppInitServiceState::new(Rc::new(self.rmap), self.config.clone());
@@ -582,8 +783,7 @@ impl TestRequest {
 
     /// Complete request creation and generate `HttpRequest` instance
     pub fn to_http_request(mut self) -> HttpRequest {
-        let (mut head, _) = self.finish().into_parts();
-        head.peer_addr = self.peer_addr;
+        let (head, _) = self.finish().into_parts();
         self.path.get_mut().update(&head.uri);
 
         let app_state = AppInitServiceState::new(Rc::new(self.rmap), self.config.clone());
@@ -593,8 +793,7 @@ impl TestRequest {
 
     /// Complete request creation and generate `HttpRequest` and `Payload` instances
     pub fn to_http_parts(mut self) -> (HttpRequest, Payload) {
-        let (mut head, payload) = self.finish().into_parts();
-        head.peer_addr = self.peer_addr;
+        let (head, payload) = self.finish().into_parts();
         self.path.get_mut().update(&head.uri);
 
         let app_state = AppInitServiceState::new(Rc::new(self.rmap), self.config.clone());
@@ -628,7 +827,135 @@ mod tests {
     use serde::{Deserialize, Serialize};
 
     use super::*;
-    use crate::{http::header, web, App, HttpResponse, Responder};
+    use crate::{
+        dev::{ConnectionInfoConfig, ResolvedClientIp, TrustedProxies},
+        http::header,
+        web, App, HttpResponse, Responder,
+    };
+
+    #[test]
+    fn test_force_connection_info() {
+        let req = TestRequest::default()
+            .forwarded_chain(&["192.0.2.60"])
+            .forwarded_proto("https")
+            .forwarded_host("rust-lang.org")
+            .force_scheme("http")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "http");
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+
+        let req = TestRequest::with_uri("/")
+            .insert_header((header::HOST, "rust-lang.org"))
+            .force_host("evil.example")
+            .to_http_request();
+        assert_eq!(req.connection_info().host(), "evil.example");
+
+        let req = TestRequest::default()
+            .peer_addr("127.0.0.1:8080".parse().unwrap())
+            .force_realip("not-an-ip")
+            .to_srv_request();
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("not-an-ip"));
+        assert_eq!(info.remote_addr(), Some("127.0.0.1:8080"));
+
+        // values that are not forced are resolved with the config from app data
+        let req = TestRequest::default()
+            .peer_addr("192.0.2.60:8080".parse().unwrap())
+            .insert_header(("x-forwarded-for", "198.51.100.17"))
+            .app_data(
+                ConnectionInfoConfig::default()
+                    .trusted_proxies(TrustedProxies::new().ip("10.0.0.1".parse().unwrap())),
+            )
+            .force_host("rust-lang.org")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60:8080"));
+
+        let req = TestRequest::default()
+            .force_realip("192.0.2.60")
+            .to_http_request();
+        assert_eq!(
+            req.extensions().get::<ResolvedClientIp>(),
+            Some(&ResolvedClientIp("192.0.2.60".parse().unwrap()))
+        );
+
+        // forced values are anonymized when the config asks for it
+        let req = TestRequest::default()
+            .app_data(ConnectionInfoConfig::default().anonymize_ip(true))
+            .force_realip("192.0.2.60")
+            .to_http_request();
+        assert_eq!(
+            req.connection_info().realip_remote_addr(),
+            Some("192.0.2.0")
+        );
+        assert_eq!(
+            req.extensions().get::<ResolvedClientIp>(),
+            Some(&ResolvedClientIp("192.0.2.0".parse().unwrap()))
+        );
+    }
+
+    #[test]
+    fn test_tls() {
+        let req = TestRequest::default()
+            .insert_header((header::HOST, "rust-lang.org"))
+            .sni("rust-lang.org")
+            .alpn("h2")
+            .to_http_request();
+        let info = req.connection_info();
+        assert_eq!(info.scheme(), "https");
+        assert_eq!(info.host_matches_sni(req.head()), Some(true));
+        assert!(info.scheme_is_https_strict(req.head()));
+
+        let tls = req.conn_data::<TlsInfo>().cloned().unwrap();
+        assert_eq!(tls.sni.as_deref(), Some("rust-lang.org"));
+        assert_eq!(tls.alpn.as_deref(), Some("h2"));
+
+        let req = TestRequest::default().tls(true).to_srv_request();
+        assert_eq!(req.connection_info().scheme(), "https");
+        assert_eq!(req.connection_info().host_matches_sni(req.head()), None);
+
+        let req = TestRequest::default()
+            .tls(true)
+            .conn_data(42u32)
+            .to_http_request();
+        assert_eq!(req.connection_info().scheme(), "https");
+        assert_eq!(req.conn_data::<u32>(), Some(&42));
+
+        let req = TestRequest::default()
+            .sni("rust-lang.org")
+            .tls(false)
+            .to_http_request();
+        assert_eq!(req.connection_info().scheme(), "http");
+        assert!(req.conn_data::<TlsInfo>().is_none());
+    }
+
+    #[test]
+    fn test_forwarded_chain() {
+        let req = TestRequest::default()
+            .forwarded_chain(&["192.0.2.60", "198.51.100.17", "[2001:db8:cafe::17]:4711"])
+            .forwarded_host("rust-lang.org")
+            .to_http_request();
+        assert_eq!(
+            req.headers().get(header::FORWARDED).unwrap(),
+            r#"for=192.0.2.60;host=rust-lang.org, for=198.51.100.17, for="[2001:db8:cafe::17]:4711""#
+        );
+
+        let info = req.connection_info();
+        assert_eq!(info.realip_remote_addr(), Some("192.0.2.60"));
+        assert_eq!(info.host(), "rust-lang.org");
+        assert_eq!(info.scheme(), "http");
+
+        let req = TestRequest::default()
+            .forwarded_proto("https")
+            .to_http_request();
+        assert_eq!(req.headers().get(header::FORWARDED).unwrap(), "proto=https");
+
+        let req = TestRequest::default().to_http_request();
+        assert!(!req.headers().contains_key(header::FORWARDED));
+    }
 
     #[actix_rt::test]
     async fn test_basics() {
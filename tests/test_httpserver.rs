@@ -147,3 +147,72 @@ async fn test_start_ssl() {
     thread::sleep(Duration::from_millis(100));
     let _ = sys.stop();
 }
+
+#[actix_rt::test]
+async fn test_on_connect_conn_data_shared_by_keep_alive_requests() {
+    use std::{
+        io::{Read as _, Write as _},
+        net::TcpStream,
+        sync::mpsc,
+        thread,
+    };
+
+    use actix_web::{
+        dev::{ConnectionData, TlsInfo},
+        web, App, HttpRequest, HttpServer,
+    };
+
+    let addr = actix_test::unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let sys = actix_rt::System::new();
+
+        sys.block_on(async {
+            let srv = HttpServer::new(|| {
+                App::new().default_service(web::to(
+                    |req: HttpRequest, id: ConnectionData<u32>| async move {
+                        let merged = req.extensions().contains::<TlsInfo>();
+                        let info = req.connection_info();
+                        format!("{} {} {} {};", info.scheme(), info.host(), *id, merged)
+                    },
+                ))
+            })
+            .on_connect(|_, data| {
+                data.insert(TlsInfo::default());
+            })
+            .on_connect_conn_data(|_, conn_data| {
+                conn_data.insert(7u32);
+            })
+            .workers(1)
+            .disable_signals()
+            .bind(addr)
+            .unwrap()
+            .run();
+
+            let _ = tx.send((srv, actix_rt::System::current()));
+        });
+
+        let _ = sys.run();
+    });
+    let (srv, sys) = rx.recv().unwrap();
+
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(
+            b"GET / HTTP/1.1\r\nHost: first.example\r\n\r\n\
+              GET / HTTP/1.1\r\nHost: second.example\r\nConnection: close\r\n\r\n",
+        )
+        .unwrap();
+
+    let mut res = String::new();
+    stream.read_to_string(&mut res).unwrap();
+
+    // on-connect data is merged into the first request's extensions, while connection data and
+    // the TLS flag recorded from it persist as the headers are parsed again
+    assert!(res.contains("https first.example 7 true;"), "{}", res);
+    assert!(res.contains("https second.example 7 false;"), "{}", res);
+
+    let _ = srv.stop(false);
+    let _ = sys.stop();
+}
@@ -1077,3 +1077,39 @@ async fn test_data_drop() {
 
     assert_eq!(num.load(Ordering::SeqCst), 0);
 }
+
+#[actix_rt::test]
+async fn test_connection_info_keep_alive() {
+    use std::net;
+
+    use actix_web::dev::ConnectionInfo;
+
+    let srv = actix_test::start_with(actix_test::config().h1(), || {
+        App::new().service(web::resource("/").route(web::to(|conn: ConnectionInfo| {
+            HttpResponse::Ok().body(format!(
+                "[{} {} {}]",
+                conn.scheme(),
+                conn.host(),
+                conn.realip_remote_addr().unwrap()
+            ))
+        })))
+    });
+
+    // both requests are sent over the same connection with different forwarding headers
+    let mut stream = net::TcpStream::connect(srv.addr()).unwrap();
+    let _ = stream.write_all(
+        b"GET / HTTP/1.1\r\n\
+        Host: rust-lang.org\r\n\
+        Forwarded: for=192.0.2.60;proto=https\r\n\r\n\
+        GET / HTTP/1.1\r\n\
+        Host: actix.rs\r\n\
+        Connection: close\r\n\r\n",
+    );
+    let mut data = String::new();
+    let _ = stream.read_to_string(&mut data);
+
+    let peer_ip = stream.local_addr().unwrap().ip();
+    let first = data.find("[https rust-lang.org 192.0.2.60]").unwrap();
+    let second = data.find(&format!("[http actix.rs {}:", peer_ip)).unwrap();
+    assert!(first < second);
+}